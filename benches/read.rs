@@ -0,0 +1,59 @@
+/* Reading manifests into memory against mapping them, at sizes around
+ * `MAP_THRESHOLD`. Run with `cargo bench`. */
+
+use {
+	std::{
+		fs,
+		hint::black_box,
+		path::Path,
+		time::{Duration, Instant},
+	},
+	memmap2::Mmap,
+	serde::de::IgnoredAny,
+};
+
+const RUNS: u32 = 200;
+
+/* A manifest of about `size` bytes, most of it in `notes` like big ones have. */
+fn manifest(size: usize) -> String {
+	let note = format!("\"{}\"", "x".repeat(100));
+	let notes = vec![note.as_str(); size / (note.len() + 1)].join(",");
+	format!(r#"{{"version": "1.0", "description": "big", "notes": [{notes}]}}"#)
+}
+
+/* The fastest of `RUNS` runs, as the others only add noise. */
+fn fastest(mut run: impl FnMut()) -> Duration {
+	(0..RUNS)
+		.map(|_| {
+			let start = Instant::now();
+			run();
+			start.elapsed()
+		})
+		.min()
+		.unwrap()
+}
+
+fn read(path: &Path) {
+	let content = fs::read(path).unwrap();
+	black_box(serde_json::from_slice::<IgnoredAny>(&content).unwrap());
+}
+
+fn map(path: &Path) {
+	let file = fs::File::open(path).unwrap();
+	/* SAFETY: Nothing else touches the file meanwhile. */
+	let content = unsafe { Mmap::map(&file) }.unwrap();
+	black_box(serde_json::from_slice::<IgnoredAny>(&content).unwrap());
+}
+
+fn main() {
+	let dir = std::env::temp_dir().join(format!("scoop-searchr-bench-{}", std::process::id()));
+	fs::create_dir_all(&dir).unwrap();
+	for kib in [4, 16, 64, 256, 1024, 4096] {
+		let path = dir.join(format!("{kib}.json"));
+		fs::write(&path, manifest(kib * 1024)).unwrap();
+		let read = fastest(|| read(&path));
+		let map = fastest(|| map(&path));
+		println!("{kib:>5} KiB: read {read:>10.1?}, map {map:>10.1?}");
+	}
+	fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,162 @@
+use {
+	std::{
+		collections::HashSet,
+		fs,
+		io::{self, Read},
+		ops::ControlFlow,
+		path::{Component, Path, PathBuf},
+		time::{Duration, Instant, SystemTime},
+	},
+	flate2::read::GzDecoder,
+	super::{
+		BucketResults, Error, Query, Result, Stats,
+		accept_manifest, looks_like_manifest, parse_manifest, sort_results,
+	},
+};
+
+/* Name of the bucket archived at `path`, after the file less its extensions. */
+fn archive_name(path: &Path) -> String {
+	let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+	[".tar.gz", ".tgz"].iter()
+		.find_map(|ext| name.strip_suffix(ext))
+		.unwrap_or(&name)
+		.to_string()
+}
+
+/* Hand each regular file in the `.tar.gz` at `path` to `each` as it is read,
+ * along with its path in there less any leading `./`, until `each` breaks. */
+fn each_file(
+	path: &Path,
+	mut each: impl FnMut(PathBuf, &mut tar::Entry<'_, GzDecoder<fs::File>>) -> io::Result<ControlFlow<()>>,
+) -> io::Result<()> {
+	let mut archive = tar::Archive::new(GzDecoder::new(fs::File::open(path)?));
+	for entry in archive.entries()? {
+		let mut entry = entry?;
+		if !entry.header().entry_type().is_file() {
+			continue
+		}
+		let path = entry.path()?.components()
+			.filter(|part| part != &Component::CurDir)
+			.collect::<PathBuf>();
+		if each(path, &mut entry)?.is_break() {
+			break
+		}
+	}
+	Ok(())
+}
+
+/* Paths of the manifests in the archive at `path`, as `manifest_files` would
+ * find them once extracted: under a single directory everything is packed
+ * into, like archives of repositories have, its `bucket` directory if there
+ * is one, at the top of that only unless `recursive` or there are none. */
+fn manifest_paths(path: &Path, recursive: bool) -> io::Result<HashSet<PathBuf>> {
+	let mut files = Vec::new();
+	each_file(path, |path, _| {
+		files.push(path);
+		Ok(ControlFlow::Continue(()))
+	})?;
+
+	let root = match files.first().and_then(|path| path.components().next()) {
+		Some(top) if files.iter().all(|path| path.components().count() > 1 && path.starts_with(top)) => PathBuf::from(&top),
+		_ => PathBuf::new(),
+	};
+	let separate = root.join("bucket");
+	let base = if files.iter().any(|path| path.starts_with(&separate)) { separate } else { root };
+
+	let manifests = files.into_iter()
+		.filter(|path| path.extension().map(|ext| ext.to_str()) == Some(Some("json")))
+		.filter_map(|path| {
+			let relative = path.strip_prefix(&base).ok()?.to_owned();
+			let hidden = relative.parent()
+				.is_some_and(|dirs| dirs.iter().any(|dir| dir.to_string_lossy().starts_with('.')));
+			(!hidden).then_some((path, relative.components().count() == 1))
+		})
+		.collect::<Vec<_>>();
+	let top_only = !recursive && manifests.iter().any(|&(_, top)| top);
+	Ok(manifests.into_iter()
+		.filter(|&(_, top)| top || !top_only)
+		.map(|(path, _)| path)
+		.collect())
+}
+
+/// Like [`search_dirs`](super::search_dirs), but searching buckets packed
+/// into the `.tar.gz` files at `archives` without extracting them, each named
+/// after its archive.
+pub fn search_archives(archives: &[PathBuf], queries: &[Query]) -> Result<(Vec<BucketResults>, Stats)> {
+	let mut results = queries.iter().map(|_| BucketResults::new()).collect::<Vec<_>>();
+	let mut stats = Stats::default();
+	let mut remaining = queries.iter().map(|query| query.limit).collect::<Vec<_>>();
+	let recursive = queries.first().is_some_and(|query| query.recursive);
+	let done = |remaining: &[Option<usize>]| remaining.iter().all(|remaining| *remaining == Some(0));
+
+	for archive in archives {
+		if done(&remaining) {
+			break
+		}
+		let start = Instant::now();
+		let bucket = archive_name(archive);
+		let read_error = |source| Error::ArchiveRead { path: archive.clone(), source };
+		/* Which files are manifests depends on all that is in there, so list
+		 * it first, then read and match them one by one. */
+		let manifests = manifest_paths(archive, recursive).map_err(read_error)?;
+		let mut found = queries.iter().map(|_| Vec::new()).collect::<Vec<_>>();
+		let mut unversioned = Vec::new();
+
+		each_file(archive, |path, entry| {
+			if !manifests.contains(&path) {
+				return Ok(ControlFlow::Continue(()))
+			}
+			let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(entry.header().mtime().unwrap_or(0));
+			let mut content = Vec::new();
+			entry.read_to_end(&mut content)?;
+			let manifest = match parse_manifest(&content) {
+				Ok(manifest) if manifest.is_app() => manifest,
+				Ok(_) => {
+					stats.not_apps.push(archive.join(&path));
+					return Ok(ControlFlow::Continue(()))
+				},
+				Err(_) if !looks_like_manifest(&content) => return Ok(ControlFlow::Continue(())),
+				Err(source) => {
+					stats.failed.push(Error::ManifestParse { path: archive.join(&path), source });
+					return Ok(ControlFlow::Continue(()))
+				},
+			};
+			stats.manifests += 1;
+			stats.parsed += 1;
+			let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+			for ((query, found), remaining) in queries.iter().zip(&mut found).zip(&mut remaining) {
+				if *remaining == Some(0) {
+					stats.limited = true;
+					continue
+				}
+				if let Some(entry) = accept_manifest(query, name.clone(), manifest.clone(), Some(modified), None, &mut unversioned) {
+					found.push(entry);
+					if let Some(remaining) = remaining {
+						*remaining -= 1;
+					}
+				}
+			}
+			Ok(if done(&remaining) {
+				stats.limited = true;
+				ControlFlow::Break(())
+			} else {
+				ControlFlow::Continue(())
+			})
+		}).map_err(read_error)?;
+
+		for (results, found) in results.iter_mut().zip(found) {
+			if !found.is_empty() {
+				results.push((bucket.clone(), found));
+			}
+		}
+		stats.unversioned.extend(unversioned);
+		stats.buckets += 1;
+		stats.scanned.push(bucket);
+		stats.elapsed += start.elapsed();
+	}
+
+	for (results, query) in results.iter_mut().zip(queries) {
+		sort_results(results, query);
+	}
+	Ok((results, stats))
+}
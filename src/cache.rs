@@ -0,0 +1,79 @@
+use {
+	std::{
+		collections::HashMap,
+		fs,
+		path::{Path, PathBuf},
+		time::SystemTime,
+	},
+	serde::{Deserialize, Serialize},
+	super::{Error, Manifest, Result, cache_dir},
+};
+
+/* Bump whenever `Manifest` changes, so stale caches are thrown away instead of
+ * silently missing the new fields. */
+const FORMAT: u32 = 6;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct CachedManifest {
+	pub(crate) modified: SystemTime,
+	pub(crate) manifest: Manifest,
+}
+
+/* Parsed manifests of each bucket directory, keyed by file name. */
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct Cache {
+	format: u32,
+	buckets: HashMap<PathBuf, HashMap<String, CachedManifest>>,
+	#[serde(skip)]
+	dirty: bool,
+}
+
+impl Default for Cache {
+	fn default() -> Self {
+		Self {
+			format: FORMAT,
+			buckets: HashMap::new(),
+			dirty: false,
+		}
+	}
+}
+
+impl Cache {
+	pub(crate) fn path(root: &Path) -> PathBuf {
+		cache_dir(root).join("scoop-searchr.json")
+	}
+
+	/* A missing, unreadable or outdated cache is simply an empty one. */
+	pub(crate) fn load(path: &Path) -> Self {
+		fs::read(path).ok()
+			.and_then(|content| serde_json::from_slice::<Self>(&content).ok())
+			.filter(|cache| cache.format == FORMAT)
+			.unwrap_or_default()
+	}
+
+	pub(crate) fn save(&self, path: &Path) -> Result<()> {
+		if !self.dirty {
+			return Ok(())
+		}
+		if let Some(parent) = path.parent() {
+			fs::create_dir_all(parent)
+				.map_err(|source| Error::CacheWrite { path: path.to_owned(), source })?;
+		}
+		let content = serde_json::to_vec(self)
+			.map_err(|e| Error::CacheWrite { path: path.to_owned(), source: e.into() })?;
+		fs::write(path, content).map_err(|source| Error::CacheWrite { path: path.to_owned(), source })
+	}
+
+	pub(crate) fn mark_dirty(&mut self) {
+		self.dirty = true;
+	}
+
+	pub(crate) fn take_bucket(&mut self, base: &Path) -> HashMap<String, CachedManifest> {
+		self.buckets.remove(base).unwrap_or_default()
+	}
+
+	pub(crate) fn put_bucket(&mut self, base: &Path, manifests: HashMap<String, CachedManifest>, changed: bool) {
+		self.dirty |= changed;
+		self.buckets.insert(base.to_owned(), manifests);
+	}
+}
@@ -0,0 +1,70 @@
+use {
+	std::{io, path::PathBuf},
+	thiserror::Error,
+};
+
+/// Everything that can go wrong searching buckets.
+#[derive(Error, Debug)]
+pub enum Error {
+	#[error("can not locate user home directory")]
+	HomeDirNotFound,
+	/// Neither `SCOOP`, the scoop config nor the default location lead to an
+	/// existing scoop root.
+	#[error("scoop root {0:?} does not exist")]
+	ScoopRootNotFound(PathBuf),
+	/// An extra root listed in the scoop config is not there.
+	#[error("extra root {0:?} does not exist")]
+	ExtraRootNotFound(PathBuf),
+	/// A bucket asked for is in none of the scoop roots.
+	#[error("bucket '{0}' does not exist")]
+	BucketNotFound(String),
+	/// A link in a buckets directory leads nowhere.
+	#[error("bucket '{0}' links to a missing directory")]
+	BucketLinkDangling(String),
+	#[error("failed to list buckets directory {path:?}")]
+	BucketsDirMissing {
+		path: PathBuf,
+		source: io::Error,
+	},
+	#[error("failed to list manifests in {path:?}")]
+	ManifestsDir {
+		path: PathBuf,
+		source: io::Error,
+	},
+	#[error("failed to read manifest {path:?}")]
+	ManifestRead {
+		path: PathBuf,
+		source: io::Error,
+	},
+	#[error("failed to parse manifest {path:?}")]
+	ManifestParse {
+		path: PathBuf,
+		source: serde_json::Error,
+	},
+	#[cfg(feature = "archive")]
+	#[error("failed to read archive {path:?}")]
+	ArchiveRead {
+		path: PathBuf,
+		source: io::Error,
+	},
+	#[cfg(feature = "regex")]
+	#[error("invalid regular expression {pattern:?}")]
+	InvalidRegex {
+		pattern: String,
+		source: regex::Error,
+	},
+	#[error("invalid glob pattern {pattern:?}")]
+	InvalidGlob {
+		pattern: String,
+		source: globset::Error,
+	},
+	#[error("{0:?} is not a dotted numeric version")]
+	InvalidVersion(String),
+	#[error("failed to write cache {path:?}")]
+	CacheWrite {
+		path: PathBuf,
+		source: io::Error,
+	},
+	#[error("failed to start worker threads")]
+	ThreadPool(#[from] rayon::ThreadPoolBuildError),
+}
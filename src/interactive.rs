@@ -0,0 +1,124 @@
+use {
+	std::io::{self, Stderr},
+	anyhow::Result,
+	ratatui::{
+		Terminal,
+		backend::CrosstermBackend,
+		crossterm::{
+			event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+			execute,
+			terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+		},
+		layout::{Constraint, Layout},
+		style::Style,
+		widgets::{List, ListState, Paragraph},
+	},
+	scoop_searchr::{FindEntry, Index, Query},
+};
+
+/* Results of `query` over `index` as `bucket/name` and a line to show. */
+fn results(index: &Index, query: &Query) -> Vec<(String, String)> {
+	index.search(query)
+		.into_iter()
+		.flat_map(|(bucket, entries)| entries.into_iter().map(move |entry| {
+			let FindEntry { name, version, description, .. } = entry;
+			let version = version.as_deref().unwrap_or("unknown");
+			let line = match description {
+				Some(description) => format!("{bucket}/{name} ({version}): {description}"),
+				None => format!("{bucket}/{name} ({version})"),
+			};
+			(format!("{bucket}/{name}"), line)
+		}))
+		.collect()
+}
+
+/// Let the user pick an app among the results of what they type, starting
+/// with `input`, searching `index` with queries from `make_query`. Returns
+/// the picked `bucket/name`, or `None` if they gave up.
+pub(crate) fn pick(
+	index: &Index,
+	input: String,
+	make_query: impl Fn(&str) -> Result<Query>,
+) -> Result<Option<String>> {
+	/* Draw to standard error, leaving standard output to the pick for
+	 * piping into `scoop install`. */
+	terminal::enable_raw_mode()?;
+	execute!(io::stderr(), EnterAlternateScreen)?;
+	let picked = Terminal::new(CrosstermBackend::new(io::stderr()))
+		.map_err(anyhow::Error::from)
+		.and_then(|mut terminal| run(&mut terminal, index, input, make_query));
+	execute!(io::stderr(), LeaveAlternateScreen)?;
+	terminal::disable_raw_mode()?;
+	picked
+}
+
+/* Search anew for `input`, or tell why not. What was found before is kept
+ * while a term is invalid, like a regular expression half typed. */
+fn refresh(
+	index: &Index,
+	make_query: impl Fn(&str) -> Result<Query>,
+	input: &str,
+	found: &mut Vec<(String, String)>,
+	state: &mut ListState,
+) -> Option<String> {
+	match make_query(input) {
+		Ok(query) => {
+			*found = results(index, &query);
+			state.select((!found.is_empty()).then_some(0));
+			None
+		},
+		Err(e) => Some(e.to_string()),
+	}
+}
+
+fn run(
+	terminal: &mut Terminal<CrosstermBackend<Stderr>>,
+	index: &Index,
+	mut input: String,
+	make_query: impl Fn(&str) -> Result<Query>,
+) -> Result<Option<String>> {
+	let mut found = Vec::new();
+	let mut state = ListState::default();
+	let mut error = refresh(index, &make_query, &input, &mut found, &mut state);
+
+	loop {
+		terminal.draw(|frame| {
+			let [list_area, input_area] = Layout::vertical([Constraint::Min(1), Constraint::Length(1)])
+				.areas(frame.area());
+			let list = List::new(found.iter().map(|(_, line)| line.as_str()))
+				.highlight_style(Style::new().reversed())
+				.highlight_symbol("> ");
+			frame.render_stateful_widget(list, list_area, &mut state);
+			let status = match &error {
+				Some(e) => format!("  ({e})"),
+				None => format!("  [{} results]", found.len()),
+			};
+			frame.render_widget(Paragraph::new(format!("> {input}{status}")), input_area);
+			frame.set_cursor_position((input_area.x + 2 + input.chars().count() as u16, input_area.y));
+		})?;
+
+		let Event::Key(key) = event::read()? else {
+			continue
+		};
+		if key.kind != KeyEventKind::Press {
+			continue
+		}
+		match key.code {
+			KeyCode::Esc => return Ok(None),
+			KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(None),
+			KeyCode::Enter => return Ok(state.selected().and_then(|i| found.get(i)).map(|(app, _)| app.clone())),
+			KeyCode::Up => state.select_previous(),
+			/* Not `select_next`, which goes past the end until drawn again. */
+			KeyCode::Down => state.select(state.selected().map(|i| (i + 1).min(found.len().saturating_sub(1)))),
+			KeyCode::Backspace => {
+				input.pop();
+				error = refresh(index, &make_query, &input, &mut found, &mut state);
+			},
+			KeyCode::Char(c) => {
+				input.push(c);
+				error = refresh(index, &make_query, &input, &mut found, &mut state);
+			},
+			_ => (),
+		}
+	}
+}
@@ -0,0 +1,2440 @@
+use {
+	std::{
+		borrow::Cow,
+		cmp::{Ordering, Reverse},
+		ffi::OsStr,
+		collections::{BTreeMap, HashMap, HashSet, VecDeque},
+		fs,
+		io::{self, Read},
+		ops::{ControlFlow, Deref},
+		path::{Path, PathBuf},
+		str::FromStr,
+		sync::mpsc,
+		time::{Duration, Instant, SystemTime},
+	},
+	serde::{Deserialize, Serialize},
+	globset::{GlobBuilder, GlobMatcher},
+	caseless::Caseless,
+	rayon::{ThreadPool, prelude::*},
+	memmap2::Mmap,
+	unicode_normalization::{UnicodeNormalization, is_nfc},
+	cache::{Cache, CachedManifest},
+};
+
+#[cfg(feature = "regex")]
+use regex::{Regex, RegexBuilder};
+#[cfg(feature = "fuzzy")]
+use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
+
+#[cfg(feature = "archive")]
+mod archive;
+mod cache;
+mod error;
+
+#[cfg(feature = "archive")]
+pub use archive::search_archives;
+pub use error::Error;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Deserialize, Debug)]
+struct ScoopConfig {
+	root_path: Option<PathBuf>,
+	extra_roots: Option<Vec<PathBuf>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+enum ManifestBinItem {
+	Path(PathBuf),
+	Command(Vec<String>),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+enum ManifestBinField {
+	Path(PathBuf),
+	PathOrCommandList(Vec<ManifestBinItem>),
+}
+
+impl ManifestBinField {
+	/* Paths of bins along with the names of their shims, if aliased. */
+	fn into_bins(self) -> Vec<(PathBuf, Option<String>)> {
+		match self {
+			Self::Path(path) => vec![(path, None)],
+			Self::PathOrCommandList(list) => list
+				.into_iter()
+				.filter_map(|item| match item {
+					/* `[executable, alias, arguments...]` */
+					ManifestBinItem::Command(command) => {
+						let mut command = command.into_iter();
+						Some((PathBuf::from(command.next()?), command.next()))
+					},
+					ManifestBinItem::Path(path) => Some((path, None)),
+				})
+				.collect(),
+		}
+	}
+}
+
+/* What an `architecture` entry may override, only bins of interest here. */
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct ManifestArchitecture {
+	bin: Option<ManifestBinField>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+enum StringOrList {
+	String(String),
+	List(Vec<String>),
+}
+
+impl StringOrList {
+	fn into_vec(self) -> Vec<String> {
+		match self {
+			Self::String(string) => vec![string],
+			Self::List(list) => list,
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+enum ManifestLicense {
+	Identifier(String),
+	Object {
+		identifier: String,
+		url: Option<String>,
+	},
+}
+
+impl ManifestLicense {
+	fn identifier(&self) -> &str {
+		let (Self::Identifier(identifier) | Self::Object { identifier, .. }) = self;
+		identifier
+	}
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Manifest {
+	/* Left out by some nightly and meta manifests. */
+	version: Option<String>,
+	bin: Option<ManifestBinField>,
+	/* Keyed by `64bit`, `32bit` and `arm64`. */
+	architecture: Option<BTreeMap<String, ManifestArchitecture>>,
+	description: Option<String>,
+	suggest: Option<HashMap<String, StringOrList>>,
+	shortcuts: Option<Vec<Vec<String>>>,
+	homepage: Option<String>,
+	license: Option<ManifestLicense>,
+	depends: Option<StringOrList>,
+	notes: Option<StringOrList>,
+	/* Shaped like bins, `[source, target]` where persisted elsewhere. */
+	persist: Option<ManifestBinField>,
+}
+
+impl Manifest {
+	/* With every field optional, about any JSON object parses as a manifest,
+	 * so one without a version needs something only an app would have. */
+	fn is_app(&self) -> bool {
+		self.version.is_some()
+			|| self.bin.is_some()
+			|| self.architecture.is_some()
+			|| self.description.is_some()
+			|| self.homepage.is_some()
+			|| self.depends.is_some()
+	}
+}
+
+/* Blank out `//` and `/* */` comments and trailing commas, which some
+ * hand-edited manifests have. */
+fn strip_jsonc(content: &[u8]) -> Vec<u8> {
+	let mut stripped = Vec::with_capacity(content.len());
+	let mut i = 0;
+	let mut in_string = false;
+	while i < content.len() {
+		let byte = content[i];
+		if in_string {
+			stripped.push(byte);
+			if byte == b'\\' {
+				if let Some(&escaped) = content.get(i + 1) {
+					stripped.push(escaped);
+					i += 1;
+				}
+			} else if byte == b'"' {
+				in_string = false;
+			}
+		} else if content[i..].starts_with(b"//") {
+			while i < content.len() && content[i] != b'\n' {
+				stripped.push(b' ');
+				i += 1;
+			}
+			continue
+		} else if content[i..].starts_with(b"/*") {
+			let end = content[i + 2..].windows(2)
+				.position(|window| window == b"*/")
+				.map_or(content.len(), |end| i + 2 + end + 2);
+			stripped.extend(content[i..end].iter().map(|&byte| if byte == b'\n' { b'\n' } else { b' ' }));
+			i = end;
+			continue
+		} else {
+			if byte == b'}' || byte == b']' {
+				if let Some(last) = stripped.iter().rposition(|byte| !byte.is_ascii_whitespace()) {
+					if stripped[last] == b',' {
+						stripped[last] = b' ';
+					}
+				}
+			}
+			in_string = byte == b'"';
+			stripped.push(byte);
+		}
+		i += 1;
+	}
+	stripped
+}
+
+fn decode_utf16(content: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Vec<u8> {
+	let units = content.chunks(2)
+		.map(|pair| from_bytes([pair[0], pair.get(1).copied().unwrap_or(0)]))
+		.collect::<Vec<_>>();
+	String::from_utf16_lossy(&units).into_bytes()
+}
+
+/* Manifests should be UTF-8, but some editors add a BOM or save as UTF-16. */
+fn decode(content: &[u8]) -> Cow<'_, [u8]> {
+	if let Some(content) = content.strip_prefix(b"\xEF\xBB\xBF") {
+		Cow::Borrowed(content)
+	} else if let Some(content) = content.strip_prefix(b"\xFF\xFE") {
+		Cow::Owned(decode_utf16(content, u16::from_le_bytes))
+	} else if let Some(content) = content.strip_prefix(b"\xFE\xFF") {
+		Cow::Owned(decode_utf16(content, u16::from_be_bytes))
+	} else {
+		Cow::Borrowed(content)
+	}
+}
+
+/* Manifests at least this large are mapped rather than read, below it the
+ * mapping costs about as much as copying does, or more; see `benches/read.rs`. */
+const MAP_THRESHOLD: u64 = 1024 * 1024;
+
+enum Content {
+	Read(Vec<u8>),
+	Mapped(Mmap),
+}
+
+impl Deref for Content {
+	type Target = [u8];
+
+	fn deref(&self) -> &[u8] {
+		match self {
+			Self::Read(content) => content,
+			Self::Mapped(map) => map,
+		}
+	}
+}
+
+fn read_manifest(path: &Path) -> io::Result<Content> {
+	let mut file = fs::File::open(path)?;
+	let len = file.metadata()?.len();
+	if len >= MAP_THRESHOLD {
+		/* SAFETY: The mapping is only read from while parsing. Git, and so
+		 * `scoop update`, replaces files instead of writing them in place,
+		 * so updating buckets meanwhile leaves it intact. */
+		if let Ok(map) = unsafe { Mmap::map(&file) } {
+			return Ok(Content::Mapped(map))
+		}
+	}
+	let mut content = Vec::with_capacity(len as usize);
+	file.read_to_end(&mut content)?;
+	Ok(Content::Read(content))
+}
+
+fn parse_manifest(content: &[u8]) -> serde_json::Result<Manifest> {
+	let content = decode(content);
+	serde_json::from_slice(&content).or_else(|e| {
+		serde_json::from_slice(&strip_jsonc(&content)).map_err(|_| e)
+	})
+}
+
+/* Buckets may keep other JSON around, like `scoopbucket.json` or CI configs,
+ * which is valid JSON without a `version`. Anything else failing to parse is
+ * a broken manifest. */
+fn looks_like_manifest(content: &[u8]) -> bool {
+	let content = decode(content);
+	serde_json::from_slice::<serde_json::Value>(&content)
+		.or_else(|_| serde_json::from_slice(&strip_jsonc(&content)))
+		.map_or(true, |value| value.get("version").is_some())
+}
+
+/* Where scoop keeps its config, and so do we. */
+fn config_dir(user_home: &Path) -> PathBuf {
+	std::env::var("XDG_CONFIG_HOME")
+		.map_or(user_home.join(".config"), PathBuf::from)
+		.join("scoop")
+}
+
+fn read_config(path: &Path) -> Option<ScoopConfig> {
+	let file = fs::File::open(path).ok()?;
+	serde_json::from_reader(&file).ok()
+}
+
+fn scoop_config(user_home: &Path) -> Option<ScoopConfig> {
+	read_config(&config_dir(user_home).join("config.json"))
+}
+
+/// Where the config file of scoop-searchr would be, `searchr.toml` next to
+/// the scoop config, if there is a home directory.
+pub fn searchr_config_path() -> Option<PathBuf> {
+	directories::UserDirs::new().map(|dirs| config_dir(dirs.home_dir()).join("searchr.toml"))
+}
+
+/* Paths in environment variables set by hand often come quoted or with a
+ * trailing separator, like `"C:\Users\me\scoop\"`. Drive and filesystem
+ * roots keep theirs. */
+fn env_path(value: &str) -> PathBuf {
+	let value = value.trim().trim_matches(['"', '\'']);
+	let trimmed = value.trim_end_matches(['/', '\\']);
+	if trimmed.is_empty() || trimmed.ends_with(':') {
+		PathBuf::from(&value[..(trimmed.len() + 1).min(value.len())])
+	} else {
+		PathBuf::from(trimmed)
+	}
+}
+
+/// The scoop root: `SCOOP` if set, or the `root_path` in a `config.json` in
+/// there if any. Otherwise the `root_path` in the scoop config, or `~/scoop`.
+pub fn scoop_home() -> Result<PathBuf> {
+	if let Ok(env_var) = std::env::var("SCOOP") {
+		let env_path = env_path(&env_var);
+		if !env_path.exists() {
+			return Err(Error::ScoopRootNotFound(env_path))
+		}
+		/* A config kept along with an installation may tell where it moved. */
+		match read_config(&env_path.join("config.json")) {
+			Some(ScoopConfig { root_path: Some(root_path), .. }) if root_path.exists() => Ok(root_path),
+			_ => Ok(env_path),
+		}
+	} else {
+		let user_home = directories::UserDirs::new()
+			.ok_or(Error::HomeDirNotFound)?
+			.home_dir()
+			.to_owned();
+		if let Some(ScoopConfig { root_path: Some(root_path), .. }) = scoop_config(&user_home) {
+			return Ok(root_path)
+		}
+		let default = user_home.join("scoop");
+		if default.exists() {
+			Ok(default)
+		} else {
+			Err(Error::ScoopRootNotFound(default))
+		}
+	}
+}
+
+/// Where the cache of parsed manifests under `root` goes: `SCOOP_CACHE` if
+/// set, like scoop does for downloads, `<root>/cache` otherwise.
+pub fn cache_dir(root: &Path) -> PathBuf {
+	/* Shared by all roots when set, which is fine as the cache is keyed by
+	 * bucket directory. */
+	std::env::var("SCOOP_CACHE").map_or(root.join("cache"), |value| env_path(&value))
+}
+
+/// [`scoop_home`] followed by the `extra_roots` listed in the scoop config,
+/// for searching several scoop installations at once. Extra roots that do not
+/// exist are left out, telling of them in `warnings`.
+pub fn scoop_roots(warnings: &mut Vec<Error>) -> Result<Vec<PathBuf>> {
+	let mut roots = vec![scoop_home()?];
+	let extra_roots = directories::UserDirs::new()
+		.and_then(|dirs| scoop_config(dirs.home_dir()))
+		.and_then(|config| config.extra_roots)
+		.unwrap_or_default();
+	for root in extra_roots {
+		if !root.exists() {
+			warnings.push(Error::ExtraRootNotFound(root));
+		} else if !roots.contains(&root) {
+			roots.push(root);
+		}
+	}
+	Ok(roots)
+}
+
+/// How a search term is compared against manifest names, bins and descriptions.
+pub enum Matcher {
+	/// Substring search.
+	Substring {
+		term: String,
+		case_sensitive: bool,
+	},
+	/// Whole name or bin search, never matching free text like descriptions.
+	Exact {
+		term: String,
+		case_sensitive: bool,
+	},
+	/// Substring search, only at word boundaries.
+	Word {
+		term: String,
+		case_sensitive: bool,
+	},
+	/// Shell-style glob matching whole names and binaries.
+	Glob(GlobMatcher),
+	/// Regular expression search.
+	#[cfg(feature = "regex")]
+	Regex(Regex),
+	/// Fuzzy (subsequence) search, ranked by score.
+	#[cfg(feature = "fuzzy")]
+	Fuzzy {
+		matcher: Box<SkimMatcherV2>,
+		term: String,
+		case_sensitive: bool,
+	},
+}
+
+/* Added to scores by field, so any name match outranks any bin match, which in
+ * turn outranks any match in other fields. */
+const NAME_WEIGHT: i64 = 2 << 32;
+const BIN_WEIGHT: i64 = 1 << 32;
+
+/* Case-fold `text` unless `case_sensitive`, remembering where each byte of the
+ * result came from so that match spans can be mapped back onto `text`. */
+struct Folded<'a> {
+	text: Cow<'a, str>,
+	offsets: Option<Vec<usize>>,
+}
+
+impl Folded<'_> {
+	fn original(&self, offset: usize) -> usize {
+		self.offsets.as_ref().map_or(offset, |offsets| offsets[offset])
+	}
+}
+
+/* Compose `text` into NFC, so that `café` with a combining accent matches
+ * `café` typed with a precomposed one. Terms and fields alike are composed. */
+fn nfc(text: &str) -> Cow<'_, str> {
+	if text.is_ascii() || is_nfc(text) {
+		Cow::Borrowed(text)
+	} else {
+		Cow::Owned(text.nfc().collect())
+	}
+}
+
+fn fold(text: &str, case_sensitive: bool) -> Folded<'_> {
+	if case_sensitive {
+		Folded { text: Cow::Borrowed(text), offsets: None }
+	} else if text.is_ascii() {
+		Folded { text: Cow::Owned(text.to_ascii_lowercase()), offsets: None }
+	} else {
+		let mut folded = String::with_capacity(text.len());
+		let mut offsets = Vec::with_capacity(text.len() + 1);
+		/* Full case folding, so that e.g. `ß` matches `ss`. */
+		for (i, c) in text.char_indices() {
+			for lower in std::iter::once(c).default_case_fold() {
+				folded.push(lower);
+				offsets.extend(std::iter::repeat_n(i, lower.len_utf8()));
+			}
+		}
+		offsets.push(text.len());
+		Folded { text: Cow::Owned(folded), offsets: Some(offsets) }
+	}
+}
+
+/// A successful match of the term against a field.
+pub struct Match {
+	pub score: i64,
+	/// Byte ranges of the matched text within the field.
+	pub spans: Vec<(usize, usize)>,
+}
+
+impl Match {
+	fn with_span(start: usize, end: usize) -> Self {
+		Self { score: 0, spans: vec![(start, end)] }
+	}
+}
+
+impl Matcher {
+	pub fn substring(term: &str, case_sensitive: bool) -> Self {
+		Self::Substring {
+			term: fold(&nfc(term), case_sensitive).text.into_owned(),
+			case_sensitive,
+		}
+	}
+
+	pub fn exact(term: &str, case_sensitive: bool) -> Self {
+		Self::Exact {
+			term: fold(&nfc(term), case_sensitive).text.into_owned(),
+			case_sensitive,
+		}
+	}
+
+	pub fn word(term: &str, case_sensitive: bool) -> Self {
+		Self::Word {
+			term: fold(&nfc(term), case_sensitive).text.into_owned(),
+			case_sensitive,
+		}
+	}
+
+	#[cfg(feature = "regex")]
+	pub fn regex(pattern: &str, case_sensitive: bool) -> Result<Self> {
+		let regex = RegexBuilder::new(&nfc(pattern))
+			.case_insensitive(!case_sensitive)
+			.build()
+			.map_err(|source| Error::InvalidRegex { pattern: pattern.to_string(), source })?;
+		Ok(Self::Regex(regex))
+	}
+
+	/* A pattern without wildcards would only ever match exactly, so search it
+	 * as a substring like without `--glob`. */
+	pub fn glob(pattern: &str, case_sensitive: bool) -> Result<Self> {
+		if !pattern.contains(['*', '?', '[', '{']) {
+			return Ok(Self::substring(pattern, case_sensitive))
+		}
+		let glob = GlobBuilder::new(&nfc(pattern))
+			.case_insensitive(!case_sensitive)
+			.build()
+			.map_err(|source| Error::InvalidGlob { pattern: pattern.to_string(), source })?;
+		Ok(Self::Glob(glob.compile_matcher()))
+	}
+
+	#[cfg(feature = "fuzzy")]
+	pub fn fuzzy(term: &str, case_sensitive: bool) -> Self {
+		let matcher = SkimMatcherV2::default();
+		Self::Fuzzy {
+			matcher: Box::new(if case_sensitive {
+				matcher.respect_case()
+			} else {
+				matcher.ignore_case()
+			}),
+			term: nfc(term).into_owned(),
+			case_sensitive,
+		}
+	}
+
+	/// Match `haystack` against the term, or `None` if it does not match.
+	pub fn find(&self, haystack: &str) -> Option<Match> {
+		match self {
+			#[cfg(feature = "fuzzy")]
+			Self::Fuzzy { matcher, term, .. } => {
+				let (score, indices) = matcher.fuzzy_indices(haystack, term)?;
+				let mut spans = Vec::<(usize, usize)>::new();
+				let mut indices = indices.into_iter().peekable();
+				for (i, (start, c)) in haystack.char_indices().enumerate() {
+					if indices.next_if_eq(&i).is_none() {
+						continue
+					}
+					let end = start + c.len_utf8();
+					match spans.last_mut() {
+						Some(last) if last.1 == start => last.1 = end,
+						_ => spans.push((start, end)),
+					}
+				}
+				Some(Match { score, spans })
+			},
+			Self::Exact { term, case_sensitive } => (fold(haystack, *case_sensitive).text == term.as_str())
+				.then(|| Match::with_span(0, haystack.len())),
+			Self::Glob(glob) => glob.is_match(haystack)
+				.then(|| Match::with_span(0, haystack.len())),
+			_ => self.find_text(haystack),
+		}
+	}
+
+	/* Fuzzy matching free text like descriptions matches nearly everything,
+	 * so fall back to a substring search there. */
+	fn find_text(&self, haystack: &str) -> Option<Match> {
+		let find_folded = |term: &str, case_sensitive| {
+			let folded = fold(haystack, case_sensitive);
+			let start = folded.text.find(term)?;
+			Some(Match::with_span(folded.original(start), folded.original(start + term.len())))
+		};
+		match self {
+			Self::Substring { term, case_sensitive } => find_folded(term, *case_sensitive),
+			#[cfg(feature = "regex")]
+			Self::Regex(regex) => regex.find(haystack)
+				.map(|found| Match::with_span(found.start(), found.end())),
+			#[cfg(feature = "fuzzy")]
+			Self::Fuzzy { term, case_sensitive, .. } =>
+				find_folded(&fold(term, *case_sensitive).text, *case_sensitive),
+			Self::Word { term, case_sensitive } => {
+				let folded = fold(haystack, *case_sensitive);
+				let text = folded.text.as_ref();
+				let is_boundary = |c: Option<char>| !c.is_some_and(char::is_alphanumeric);
+				let (start, _) = text.match_indices(term.as_str()).find(|&(start, _)| {
+					is_boundary(text[..start].chars().next_back())
+						&& is_boundary(text[start + term.len()..].chars().next())
+				})?;
+				Some(Match::with_span(folded.original(start), folded.original(start + term.len())))
+			},
+			Self::Exact { .. } | Self::Glob(_) => None,
+		}
+	}
+}
+
+enum VersionPart<'a> {
+	Number(&'a str),
+	Text(&'a str),
+}
+
+fn version_parts(version: &str) -> impl Iterator<Item = VersionPart<'_>> {
+	version.split(|c: char| !c.is_alphanumeric())
+		.filter(|part| !part.is_empty())
+		.flat_map(|part| {
+			let mut rest = part;
+			std::iter::from_fn(move || {
+				let first = rest.chars().next()?;
+				let end = rest.find(|c: char| c.is_ascii_digit() != first.is_ascii_digit())
+					.unwrap_or(rest.len());
+				let (part, tail) = rest.split_at(end);
+				rest = tail;
+				Some(if first.is_ascii_digit() {
+					VersionPart::Number(part.trim_start_matches('0'))
+				} else {
+					VersionPart::Text(part)
+				})
+			})
+		})
+}
+
+/* Runs of digits and of anything else in `text`. */
+fn natural_chunks(text: &str) -> impl Iterator<Item = &str> {
+	let mut rest = text;
+	std::iter::from_fn(move || {
+		let digits = rest.chars().next()?.is_ascii_digit();
+		let end = rest.find(|c: char| c.is_ascii_digit() != digits).unwrap_or(rest.len());
+		let (chunk, tail) = rest.split_at(end);
+		rest = tail;
+		Some(chunk)
+	})
+}
+
+/* Compare names the way people would, numbers by value and text regardless
+ * of case, so `app2` comes before `App10`. Otherwise equal names are compared
+ * as they are. */
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+	let mut a_chunks = natural_chunks(a);
+	let mut b_chunks = natural_chunks(b);
+	loop {
+		let (a_chunk, b_chunk) = match (a_chunks.next(), b_chunks.next()) {
+			(None, None) => return a.cmp(b),
+			(None, Some(_)) => return Ordering::Less,
+			(Some(_), None) => return Ordering::Greater,
+			(Some(a_chunk), Some(b_chunk)) => (a_chunk, b_chunk),
+		};
+		let ordering = if a_chunk.starts_with(|c: char| c.is_ascii_digit())
+			&& b_chunk.starts_with(|c: char| c.is_ascii_digit())
+		{
+			let (a_number, b_number) = (a_chunk.trim_start_matches('0'), b_chunk.trim_start_matches('0'));
+			a_number.len().cmp(&b_number.len()).then_with(|| a_number.cmp(b_number))
+		} else {
+			a_chunk.chars().flat_map(char::to_lowercase).cmp(b_chunk.chars().flat_map(char::to_lowercase))
+		};
+		if ordering != Ordering::Equal {
+			return ordering
+		}
+	}
+}
+
+/// Compare versions part by part, numerically where both parts are numbers,
+/// so `10.0` is newer than `9.0`. Numbers are newer than text, and a version
+/// with more parts is newer than its prefix.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+	let mut a = version_parts(a);
+	let mut b = version_parts(b);
+	loop {
+		let ordering = match (a.next(), b.next()) {
+			(None, None) => return Ordering::Equal,
+			(None, Some(_)) => return Ordering::Less,
+			(Some(_), None) => return Ordering::Greater,
+			(Some(VersionPart::Number(a)), Some(VersionPart::Number(b))) =>
+				a.len().cmp(&b.len()).then_with(|| a.cmp(b)),
+			(Some(VersionPart::Number(_)), Some(VersionPart::Text(_))) => Ordering::Greater,
+			(Some(VersionPart::Text(_)), Some(VersionPart::Number(_))) => Ordering::Less,
+			(Some(VersionPart::Text(a)), Some(VersionPart::Text(b))) => a.cmp(b),
+		};
+		if ordering != Ordering::Equal {
+			return ordering
+		}
+	}
+}
+
+/// A dotted numeric version like `2.43.0`, to bound [`Query::min_version`]
+/// and [`Query::max_version`] with. Trailing zero parts are insignificant.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct NumericVersion(Vec<u64>);
+
+impl FromStr for NumericVersion {
+	type Err = Error;
+
+	fn from_str(version: &str) -> Result<Self> {
+		let mut parts = version.split('.')
+			.map(|part| part.parse::<u64>())
+			.collect::<Result<Vec<_>, _>>()
+			.map_err(|_| Error::InvalidVersion(version.to_string()))?;
+		while parts.last() == Some(&0) {
+			parts.pop();
+		}
+		Ok(Self(parts))
+	}
+}
+
+/// Which manifest fields a search considers.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Fields {
+	pub name: bool,
+	pub bin: bool,
+	pub description: bool,
+	/// Start Menu shortcut names.
+	pub shortcut: bool,
+	/// Packages suggested as optional dependencies.
+	pub suggest: bool,
+	pub homepage: bool,
+	/// License identifiers.
+	pub license: bool,
+	/// Packages depended on.
+	pub depends: bool,
+	/// Notes shown after installing.
+	pub notes: bool,
+	/// Paths of data kept across updates.
+	pub persist: bool,
+}
+
+impl Fields {
+	pub fn none() -> Self {
+		Self {
+			name: false,
+			bin: false,
+			description: false,
+			shortcut: false,
+			suggest: false,
+			homepage: false,
+			license: false,
+			depends: false,
+			notes: false,
+			persist: false,
+		}
+	}
+
+	pub fn all() -> Self {
+		Self {
+			name: true,
+			bin: true,
+			description: true,
+			shortcut: true,
+			suggest: true,
+			homepage: true,
+			license: true,
+			depends: true,
+			notes: true,
+			persist: true,
+		}
+	}
+}
+
+impl Default for Fields {
+	fn default() -> Self {
+		Self {
+			name: true,
+			bin: true,
+			description: true,
+			shortcut: true,
+			suggest: true,
+			homepage: false,
+			license: false,
+			depends: false,
+			notes: false,
+			persist: false,
+		}
+	}
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum SortBy {
+	/// Entries within each bucket by name, naturally: numbers by value and
+	/// text regardless of case.
+	Name,
+	/// Entries within each bucket by version, see [`compare_versions`].
+	Version,
+	/// Buckets by name, entries within each by name.
+	Bucket,
+	/// Entries within each bucket by when their manifest was last modified,
+	/// latest first.
+	Date,
+	/// Entries within each bucket by how closely they match: names equal to
+	/// the terms first, then names starting with them, names containing
+	/// them, bins, and matches in other fields last.
+	Rank,
+	/// Entries within each bucket by name, byte by byte instead of the
+	/// natural order of [`SortBy::Name`], so `App10` comes before `app2`.
+	Raw,
+}
+
+/// An architecture a manifest may declare bins of its own for.
+#[derive(Serialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum Arch {
+	#[serde(rename = "64bit")]
+	X64,
+	#[serde(rename = "32bit")]
+	X86,
+	#[serde(rename = "arm64")]
+	Arm64,
+}
+
+impl Arch {
+	/// The key of the architecture in manifests.
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::X64 => "64bit",
+			Self::X86 => "32bit",
+			Self::Arm64 => "arm64",
+		}
+	}
+
+	fn from_key(key: &str) -> Option<Self> {
+		[Self::X64, Self::X86, Self::Arm64].into_iter().find(|arch| arch.as_str() == key)
+	}
+}
+
+pub struct Query {
+	/// Terms that all have to match, each in any of `fields`.
+	pub matchers: Vec<Matcher>,
+	/// Terms none of which may match in any of `fields`.
+	pub excludes: Vec<Matcher>,
+	pub fields: Fields,
+	/// Only search these buckets, or all if empty.
+	pub buckets: Vec<String>,
+	/// Never search these buckets, even if also in `buckets`.
+	pub exclude_buckets: Vec<String>,
+	/// Stop searching after this many results in total.
+	pub limit: Option<usize>,
+	/// How to order results, by name unless fuzzy matching, which orders by
+	/// score.
+	pub sort: Option<SortBy>,
+	/// Reverse the order given by `sort`.
+	pub reverse: bool,
+	/// Reuse manifests parsed by earlier searches, see [`update_cache`].
+	pub cache: bool,
+	/// Only find apps that are installed, or only those that are not.
+	/// Ignored by [`find_manifests`], which does not know the scoop root.
+	pub installed: Option<bool>,
+	/// Threads to read and parse manifests with, or as many as there are
+	/// logical CPUs if `None`.
+	pub jobs: Option<usize>,
+	/// Only find apps of at least this version. Apps of versions other than
+	/// dotted numeric ones are never found then.
+	pub min_version: Option<NumericVersion>,
+	/// Only find apps of at most this version, like `min_version`.
+	pub max_version: Option<NumericVersion>,
+	/// Look for manifests in subdirectories of buckets too. Even if not,
+	/// they are looked for there in buckets without manifests at the top.
+	pub recursive: bool,
+	/// Only search bins of this architecture besides those of all, or bins of
+	/// every architecture if `None`.
+	pub arch: Option<Arch>,
+}
+
+impl Query {
+	pub fn new(matcher: Matcher) -> Self {
+		Self {
+			matchers: vec![matcher],
+			excludes: Vec::new(),
+			fields: Fields::default(),
+			buckets: Vec::new(),
+			exclude_buckets: Vec::new(),
+			limit: None,
+			sort: None,
+			reverse: false,
+			cache: false,
+			installed: None,
+			jobs: None,
+			min_version: None,
+			max_version: None,
+			recursive: false,
+			arch: None,
+		}
+	}
+
+	/* Whether an app named `name` could match, judging by its name alone.
+	 * Only ever `false` when searching names only. */
+	fn could_match_name(&self, name: &str) -> bool {
+		let names_only = Fields { name: true, ..Fields::none() };
+		if self.fields != names_only {
+			return true
+		}
+		/* File names can be decomposed, the terms are composed. */
+		let name = nfc(name);
+		self.matchers.iter().all(|matcher| matcher.find(&name).is_some())
+	}
+
+	/* Whether an app named `name` could match, judging by its raw manifest
+	 * `content`. Terms are looked for as bytes, so only plain ASCII terms
+	 * are, as JSON may escape anything else. */
+	fn could_match_content(&self, name: &str, content: &[u8]) -> bool {
+		let mut lowercase = None;
+		self.matchers.iter().all(|matcher| {
+			let (Matcher::Substring { term, case_sensitive }
+				| Matcher::Exact { term, case_sensitive }
+				| Matcher::Word { term, case_sensitive }) = matcher
+			else {
+				return true
+			};
+			if !term.is_ascii() || term.contains(['"', '\\']) || matcher.find(name).is_some() {
+				return true
+			}
+			/* Case folding may turn non-ASCII text into ASCII, like `ß` into `ss`. */
+			let content = if *case_sensitive {
+				content
+			} else {
+				lowercase.get_or_insert_with(|| match std::str::from_utf8(content) {
+					Ok(text) => fold(text, false).text.into_owned().into_bytes(),
+					Err(_) => content.to_ascii_lowercase(),
+				})
+			};
+			memchr::memmem::find(content, term.as_bytes()).is_some()
+		})
+	}
+}
+
+/// The manifest field that matched a term.
+#[derive(Serialize, PartialEq, PartialOrd, Eq, Ord, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchField {
+	Name,
+	Bin,
+	Shortcut,
+	Description,
+	Suggest,
+	Homepage,
+	License,
+	Depends,
+	Notes,
+	Persist,
+}
+
+impl MatchField {
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::Name => "name",
+			Self::Bin => "bin",
+			Self::Shortcut => "shortcut",
+			Self::Description => "description",
+			Self::Suggest => "suggest",
+			Self::Homepage => "homepage",
+			Self::License => "license",
+			Self::Depends => "depends",
+			Self::Notes => "notes",
+			Self::Persist => "persist",
+		}
+	}
+
+	fn weight(self) -> i64 {
+		match self {
+			Self::Name => NAME_WEIGHT,
+			Self::Bin => BIN_WEIGHT,
+			_ => 0,
+		}
+	}
+}
+
+/// A match in a field other than the name, bins or description.
+#[derive(Serialize, PartialEq, PartialOrd, Eq, Ord, Debug)]
+pub struct ExtraMatch {
+	pub field: MatchField,
+	pub text: String,
+	/// Byte ranges of matches within `text`.
+	#[serde(skip)]
+	pub spans: Vec<(usize, usize)>,
+}
+
+fn serialize_timestamp<S: serde::Serializer>(
+	time: &Option<SystemTime>,
+	serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+	time.and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+		.map(|since| since.as_secs())
+		.serialize(serializer)
+}
+
+#[derive(Serialize, PartialEq, PartialOrd, Eq, Ord, Debug)]
+pub struct FindEntry {
+	pub name: String,
+	/// `None` if the manifest has no version.
+	pub version: Option<String>,
+	pub bins: Vec<PathBuf>,
+	/// Architectures each of `bins` is declared for, empty if for all.
+	pub bin_archs: Vec<Vec<Arch>>,
+	pub description: Option<String>,
+	pub extra: Vec<ExtraMatch>,
+	/// Installed version, if installed.
+	pub installed: Option<String>,
+	/// SPDX identifier of the license, or whatever else the manifest says.
+	pub license: Option<String>,
+	pub matched_on: MatchField,
+	pub score: i64,
+	/// When the manifest was last modified, serialized as seconds since the
+	/// Unix epoch.
+	#[serde(serialize_with = "serialize_timestamp")]
+	pub modified: Option<SystemTime>,
+	/// Byte ranges of matches within the name. Each term only has ranges in
+	/// the field it was matched on, the others are not searched for it again.
+	#[serde(skip)]
+	pub name_spans: Vec<(usize, usize)>,
+	/// Byte ranges of matches within each of `bins`.
+	#[serde(skip)]
+	pub bin_spans: Vec<Vec<(usize, usize)>>,
+	/// Byte ranges of matches within the description.
+	#[serde(skip)]
+	pub description_spans: Vec<(usize, usize)>,
+}
+
+/// Names of buckets with matches and their matching entries.
+pub type BucketResults = Vec<(String, Vec<FindEntry>)>;
+
+/// Counters of a search, see [`search_stats`].
+#[derive(Default, Debug)]
+pub struct Stats {
+	/// Buckets walked.
+	pub buckets: usize,
+	/// Names of buckets walked, as in results.
+	pub scanned: Vec<String>,
+	/// Manifests read, whether from the cache or parsed afresh.
+	pub manifests: usize,
+	/// Manifests parsed afresh.
+	pub parsed: usize,
+	/// Manifests failing to be read or parsed.
+	pub failed: Vec<Error>,
+	/// JSON files with nothing an app manifest has, not even a version, like
+	/// `scoopbucket.json`, left out without failing.
+	pub not_apps: Vec<PathBuf>,
+	/// Names and versions of apps left out for not having a numeric version
+	/// when bounding versions.
+	pub unversioned: Vec<(String, Option<String>)>,
+	/// Manifests of the same bucket with names differing only in case, of
+	/// which one shadows the others on case-insensitive filesystems.
+	pub duplicates: Vec<Vec<PathBuf>>,
+	/// What was left out of the search other than manifests, like buckets
+	/// asked for that do not exist.
+	pub warnings: Vec<Error>,
+	/// Whether the search stopped at its limit before walking everything, or
+	/// left out matches past it.
+	pub limited: bool,
+	/// Time spent walking buckets.
+	pub elapsed: Duration,
+}
+
+/* Sort and merge overlapping spans, as several terms may match the same field. */
+fn merge_spans(mut spans: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+	spans.sort();
+	let mut merged = Vec::<(usize, usize)>::with_capacity(spans.len());
+	for (start, end) in spans {
+		match merged.last_mut() {
+			Some(last) if start <= last.1 => last.1 = last.1.max(end),
+			_ => merged.push((start, end)),
+		}
+	}
+	merged
+}
+
+/* A piece of a manifest a term may match, in a byte range `window` of `text`,
+ * so bins can be matched by file stem while still being shown in full. */
+struct Candidate {
+	field: MatchField,
+	text: String,
+	window: (usize, usize),
+	/* Architectures a bin is declared for, empty if for all. */
+	archs: Vec<Arch>,
+}
+
+impl Candidate {
+	fn new(field: MatchField, text: String) -> Self {
+		let text = match nfc(&text) {
+			Cow::Borrowed(_) => text,
+			Cow::Owned(composed) => composed,
+		};
+		let window = (0, text.len());
+		Self { field, text, window, archs: Vec::new() }
+	}
+
+	/* The name of a shim is matched as a whole, dots and all. */
+	fn alias(alias: String, archs: Vec<Arch>) -> Self {
+		Self { archs, ..Self::new(MatchField::Bin, alias) }
+	}
+
+	fn bin(path: PathBuf, archs: Vec<Arch>) -> Self {
+		let text = nfc(&path.to_string_lossy()).into_owned();
+		let path = Path::new(&text);
+		/* The whole path for odd ones without a file name, like `""` or one
+		 * ending in `..`. */
+		let window = match (path.file_name().and_then(OsStr::to_str), path.file_stem().and_then(OsStr::to_str)) {
+			(Some(name), Some(stem)) if text.ends_with(name) => {
+				let start = text.len() - name.len();
+				(start, start + stem.len())
+			},
+			_ => (0, text.len()),
+		};
+		Self { field: MatchField::Bin, text, window, archs }
+	}
+
+	fn find(&self, matcher: &Matcher) -> Option<Match> {
+		let (start, end) = self.window;
+		let text = &self.text[start..end];
+		let found = match self.field {
+			MatchField::Name | MatchField::Bin => matcher.find(text),
+			_ => matcher.find_text(text),
+		}?;
+		Some(Match {
+			score: found.score,
+			spans: found.spans.into_iter().map(|(from, to)| (from + start, to + start)).collect(),
+		})
+	}
+}
+
+fn candidates(name: &str, manifest: Manifest, fields: &Fields, arch: Option<Arch>) -> Vec<Candidate> {
+	let mut candidates = Vec::new();
+	if fields.name {
+		candidates.push(Candidate::new(MatchField::Name, name.to_string()));
+	}
+	if fields.bin {
+		let mut bins = manifest.bin.map(ManifestBinField::into_bins).unwrap_or_default()
+			.into_iter()
+			.map(|bin| (bin, Vec::new()))
+			.collect::<Vec<_>>();
+		for (key, architecture) in manifest.architecture.into_iter().flatten() {
+			if arch.is_some_and(|arch| arch.as_str() != key) {
+				continue
+			}
+			for bin in architecture.bin.map(ManifestBinField::into_bins).unwrap_or_default() {
+				match bins.iter_mut().find(|(known, _)| *known == bin) {
+					/* Declared for all architectures already. */
+					Some((_, archs)) if archs.is_empty() => (),
+					Some((_, archs)) => archs.extend(Arch::from_key(&key).filter(|arch| !archs.contains(arch))),
+					None => bins.push((bin, Arch::from_key(&key).into_iter().collect())),
+				}
+			}
+		}
+		for ((path, alias), archs) in bins {
+			/* Shims are mostly named after what they run, no need to match twice. */
+			let alias = alias.filter(|alias| {
+				path.file_stem().is_none_or(|stem| !stem.to_string_lossy().eq_ignore_ascii_case(alias))
+			});
+			if let Some(alias) = alias {
+				candidates.push(Candidate::bin(path, archs.clone()));
+				candidates.push(Candidate::alias(alias, archs));
+			} else {
+				candidates.push(Candidate::bin(path, archs));
+			}
+		}
+	}
+	if let Some(shortcuts) = manifest.shortcuts.filter(|_| fields.shortcut) {
+		/* Each is `[target, name, arguments?, icon?]`. */
+		candidates.extend(shortcuts.into_iter()
+			.filter_map(|shortcut| shortcut.into_iter().nth(1))
+			.map(|name| Candidate::new(MatchField::Shortcut, name)));
+	}
+	if let Some(description) = manifest.description.filter(|_| fields.description) {
+		candidates.push(Candidate::new(MatchField::Description, description));
+	}
+	if let Some(suggest) = manifest.suggest.filter(|_| fields.suggest) {
+		candidates.extend(suggest.into_values()
+			.flat_map(StringOrList::into_vec)
+			.map(|package| Candidate::new(MatchField::Suggest, package)));
+	}
+	if let Some(homepage) = manifest.homepage.filter(|_| fields.homepage) {
+		candidates.push(Candidate::new(MatchField::Homepage, homepage));
+	}
+	if let Some(license) = manifest.license.filter(|_| fields.license) {
+		candidates.push(Candidate::new(MatchField::License, license.identifier().to_owned()));
+	}
+	if let Some(depends) = manifest.depends.filter(|_| fields.depends) {
+		candidates.extend(depends.into_vec().into_iter().map(|package| Candidate::new(MatchField::Depends, package)));
+	}
+	if let Some(notes) = manifest.notes.filter(|_| fields.notes) {
+		candidates.extend(notes.into_vec().into_iter().map(|note| Candidate::new(MatchField::Notes, note)));
+	}
+	if let Some(persist) = manifest.persist.filter(|_| fields.persist) {
+		for (source, target) in persist.into_bins() {
+			candidates.push(Candidate::new(MatchField::Persist, source.to_string_lossy().into_owned()));
+			candidates.extend(target.map(|target| Candidate::new(MatchField::Persist, target)));
+		}
+	}
+	candidates
+}
+
+/* Each term is matched against the fields in the order of `MatchField`,
+ * stopping at the first field it matches. Every term has to match, and no
+ * excluded term may match anywhere. */
+fn match_manifest(name: String, manifest: Manifest, query: &Query) -> Option<FindEntry> {
+	let Query { matchers, excludes, fields, .. } = query;
+	let version = manifest.version.clone();
+	let license = manifest.license.as_ref().map(|license| license.identifier().to_owned());
+	let candidates = candidates(&name, manifest, fields, query.arch);
+
+	if excludes.iter().any(|matcher| candidates.iter().any(|candidate| candidate.find(matcher).is_some())) {
+		return None
+	}
+
+	let mut spans = (0..candidates.len()).map(|_| None::<Vec<_>>).collect::<Vec<_>>();
+	let mut matched_on = None;
+	let mut score = 0;
+
+	/* Each term is matched on the first field it is found in, and only that
+	 * field gets spans for it, so highlighting tells why a result is there. */
+	for matcher in matchers {
+		let mut resolved = None;
+		let mut offset = 0;
+		for group in candidates.chunk_by(|a, b| a.field == b.field) {
+			let mut best = None;
+			for (candidate, spans) in group.iter().zip(&mut spans[offset..]) {
+				if let Some(found) = candidate.find(matcher) {
+					spans.get_or_insert_with(Vec::new).extend(found.spans);
+					best = Some(best.map_or(found.score, |best: i64| best.max(found.score)));
+				}
+			}
+			offset += group.len();
+			if let Some(best) = best {
+				resolved = Some((group[0].field, group[0].field.weight() + best));
+				break
+			}
+		}
+		let (field, field_score) = resolved?;
+		matched_on = Some(matched_on.map_or(field, |matched_on: MatchField| matched_on.min(field)));
+		score += field_score;
+	}
+
+	let mut entry = FindEntry {
+		name,
+		version,
+		bins: Vec::new(),
+		bin_archs: Vec::new(),
+		description: None,
+		extra: Vec::new(),
+		installed: None,
+		license,
+		matched_on: matched_on.unwrap_or(MatchField::Name),
+		score,
+		modified: None,
+		name_spans: Vec::new(),
+		bin_spans: Vec::new(),
+		description_spans: Vec::new(),
+	};
+	for (candidate, spans) in candidates.into_iter().zip(spans) {
+		let Some(spans) = spans.map(merge_spans) else {
+			continue
+		};
+		match candidate.field {
+			/* Spans are of the composed name, the name itself is of the file
+			 * and may well be decomposed. */
+			MatchField::Name if candidate.text == entry.name => entry.name_spans = spans,
+			MatchField::Name => (),
+			MatchField::Bin => {
+				entry.bins.push(PathBuf::from(candidate.text));
+				entry.bin_spans.push(spans);
+				entry.bin_archs.push(candidate.archs);
+			},
+			MatchField::Description => {
+				entry.description = Some(candidate.text);
+				entry.description_spans = spans;
+			},
+			field => entry.extra.push(ExtraMatch { field, text: candidate.text, spans }),
+		}
+	}
+	Some(entry)
+}
+
+/// Search the bucket directory `base` for `query`, along with what it went
+/// through.
+pub fn find_manifests(base: &Path, query: &Query) -> Result<(Vec<FindEntry>, Stats)> {
+	let mut stats = Stats::default();
+	let mut results = Vec::new();
+	if query.limit != Some(0) {
+		walk_matches(manifest_files(base, query.recursive)?, std::slice::from_ref(query), None, None, None, &mut stats, |_, entry| {
+			results.push(entry);
+			if query.limit.is_some_and(|limit| results.len() >= limit) {
+				ControlFlow::Break(())
+			} else {
+				ControlFlow::Continue(())
+			}
+		})?;
+	}
+	sort_entries(&mut results, query);
+	Ok((results, stats))
+}
+
+/// The version of app `name` installed under the scoop root `root`, if any.
+pub fn installed_version(root: &Path, name: &str) -> Option<String> {
+	let current = root.join("apps").join(name).join("current");
+	/* `current` links to the directory named after the installed version. */
+	let resolved = fs::canonicalize(&current).ok()?;
+	fs::read(current.join("manifest.json")).ok()
+		.and_then(|content| parse_manifest(&content).ok())
+		.and_then(|manifest| manifest.version)
+		.or_else(|| Some(resolved.file_name()?.to_string_lossy().into_owned()))
+}
+
+/* Match a manifest against `query` and its filters. `root` is only needed to
+ * tell which apps are installed. */
+fn accept_manifest(
+	query: &Query,
+	name: String,
+	manifest: Manifest,
+	modified: Option<SystemTime>,
+	root: Option<&Path>,
+	unversioned: &mut Vec<(String, Option<String>)>,
+) -> Option<FindEntry> {
+	let mut entry = match_manifest(name, manifest, query)?;
+	entry.modified = modified;
+	if let Some(root) = root {
+		entry.installed = installed_version(root, &entry.name);
+	}
+	if query.installed.is_some_and(|installed| installed != entry.installed.is_some()) {
+		return None
+	}
+	if query.min_version.is_some() || query.max_version.is_some() {
+		let Some(Ok(version)) = entry.version.as_deref().map(str::parse::<NumericVersion>) else {
+			unversioned.push((entry.name, entry.version));
+			return None
+		};
+		if query.min_version.as_ref().is_some_and(|min| version < *min)
+			|| query.max_version.as_ref().is_some_and(|max| version > *max)
+		{
+			return None
+		}
+	}
+	Some(entry)
+}
+
+/* Walk the manifests listed for matches of any of `queries`, handing each to
+ * `each` along with the index of the query it matches, until `each` breaks. */
+fn walk_matches(
+	listing: Listing,
+	queries: &[Query],
+	cache: Option<&mut Cache>,
+	root: Option<&Path>,
+	pool: Option<&ThreadPool>,
+	stats: &mut Stats,
+	mut each: impl FnMut(usize, FindEntry) -> ControlFlow<()>,
+) -> Result<()> {
+	let start = Instant::now();
+	let mut unversioned = Vec::new();
+	walk_manifests(listing, cache, queries, pool, stats, |name, manifest, modified| {
+		let mut manifest = Some(manifest);
+		for (i, query) in queries.iter().enumerate() {
+			/* Only copy the manifest for all but the last query. */
+			let manifest = if i + 1 == queries.len() {
+				manifest.take()
+			} else {
+				manifest.clone()
+			};
+			let Some(entry) = manifest.and_then(|manifest| {
+				accept_manifest(query, name.clone(), manifest, modified, root, &mut unversioned)
+			}) else {
+				continue
+			};
+			if each(i, entry).is_break() {
+				return ControlFlow::Break(())
+			}
+		}
+		ControlFlow::Continue(())
+	})?;
+	stats.unversioned.extend(unversioned);
+	stats.buckets += 1;
+	stats.elapsed += start.elapsed();
+	Ok(())
+}
+
+/* Lower is better, see `SortBy::Rank`. */
+fn rank(entry: &FindEntry) -> u8 {
+	match entry.name_spans.first() {
+		Some(&(0, end)) if end == entry.name.len() => 0,
+		Some(&(0, _)) => 1,
+		Some(_) => 2,
+		None if !entry.bins.is_empty() => 3,
+		None => 4,
+	}
+}
+
+fn sort_entries(entries: &mut [FindEntry], query: &Query) {
+	entries.sort();
+	if query.sort != Some(SortBy::Raw) {
+		entries.sort_by(|a, b| natural_cmp(&a.name, &b.name));
+	}
+	match query.sort {
+		Some(SortBy::Rank) => entries.sort_by_key(rank),
+		/* Unknown versions come first, as the oldest. */
+		Some(SortBy::Version) => entries.sort_by(|a, b| {
+			compare_versions(a.version.as_deref().unwrap_or_default(), b.version.as_deref().unwrap_or_default())
+		}),
+		Some(SortBy::Date) => entries.sort_by_key(|entry| Reverse(entry.modified)),
+		#[cfg(feature = "fuzzy")]
+		None if matches!(query.matchers.first(), Some(Matcher::Fuzzy { .. })) =>
+			entries.sort_by_key(|entry| Reverse(entry.score)),
+		_ => (),
+	}
+	if query.reverse && query.sort != Some(SortBy::Bucket) {
+		entries.reverse();
+	}
+}
+
+/* Manifest files found in the bucket directory `base`, with their paths
+ * relative to it and when they were last modified, and subdirectories failing
+ * to be walked. */
+struct Listing {
+	base: PathBuf,
+	files: Vec<(PathBuf, String, Option<SystemTime>)>,
+	skipped: Vec<Error>,
+}
+
+/* List the manifest files in `base`. Subdirectories other than hidden ones are
+ * walked too if `recursive`, or if `base` has no manifests of its own. */
+fn manifest_files(base: &Path, recursive: bool) -> Result<Listing> {
+	let mut files = Vec::new();
+	let mut skipped = Vec::new();
+	let mut dirs = vec![base.to_owned()];
+	/* Directories may link back to where they are, so never walk one twice. */
+	let mut seen = HashSet::new();
+
+	while let Some(dir) = dirs.pop() {
+		if !seen.insert(fs::canonicalize(&dir).unwrap_or_else(|_| dir.clone())) {
+			continue
+		}
+		let walk = match dir.read_dir() {
+			Ok(walk) => walk,
+			Err(e) if dir == base => {
+				return Err(Error::ManifestsDir { path: base.to_owned(), source: e })
+			},
+			Err(source) => {
+				skipped.push(Error::ManifestsDir { path: dir, source });
+				continue
+			},
+		};
+		for maybe_entry in walk {
+			let entry = match maybe_entry {
+				Ok(entry) => entry,
+				Err(source) => {
+					skipped.push(Error::ManifestsDir { path: dir.clone(), source });
+					continue
+				},
+			};
+			let path = entry.path();
+			if path.is_dir() {
+				if !entry.file_name().to_string_lossy().starts_with('.') {
+					dirs.push(path);
+				}
+				continue
+			}
+			/* Following links, so dangling ones, sockets and the like are left
+			 * out instead of failing to read. */
+			if !path.is_file() || path.extension().map(|ext| ext.to_str()) != Some(Some("json")) {
+				continue
+			}
+			let file_name = path.strip_prefix(base).unwrap_or(&path).to_string_lossy().into_owned();
+			let modified = entry.metadata().and_then(|metadata| metadata.modified()).ok();
+			files.push((path, file_name, modified));
+		}
+		if dir == base && !recursive && !files.is_empty() {
+			break
+		}
+	}
+	Ok(Listing { base: base.to_owned(), files, skipped })
+}
+
+/* Read and parse every manifest listed, reusing unchanged ones from `cache`,
+ * until `each` breaks, counting manifests into `stats`. Manifests that can not
+ * match any of `prefilter` are still parsed, so failures and the cache do not
+ * depend on the terms, but not handed to `each`. Reading and parsing runs in
+ * `pool`, or the global one. */
+fn walk_manifests(
+	listing: Listing,
+	mut cache: Option<&mut Cache>,
+	prefilter: &[Query],
+	pool: Option<&ThreadPool>,
+	stats: &mut Stats,
+	mut each: impl FnMut(String, Manifest, Option<SystemTime>) -> ControlFlow<()>,
+) -> Result<()> {
+	let Listing { base, files, skipped } = listing;
+	stats.warnings.extend(skipped);
+	/* Only files of the same directory shadow one another. */
+	let mut names = HashMap::<_, Vec<_>>::new();
+	for (path, _, _) in &files {
+		let name = path.file_stem().unwrap().to_string_lossy().to_lowercase();
+		names.entry((path.parent(), name)).or_default().push(path.clone());
+	}
+	stats.duplicates.extend(names.into_values()
+		.filter(|paths| paths.iter().any(|path| path.file_name() != paths[0].file_name()))
+		.map(|mut paths| {
+			paths.sort();
+			paths
+		}));
+	let mut cached = cache.as_deref_mut().map(|cache| cache.take_bucket(&base));
+	let mut fresh = HashMap::new();
+	let mut parsed = 0;
+	let mut stopped = false;
+
+	/* Pick what can be reused from the cache first, so reading and parsing
+	 * the rest can be spread over threads. */
+	let mut pending = Vec::new();
+	for (path, file_name, modified) in files {
+		let reused = cached.as_mut()
+			.and_then(|cached| cached.remove(&file_name))
+			.filter(|cached| Some(cached.modified) == modified)
+			.map(|cached| cached.manifest);
+		pending.push((path, file_name, modified, reused));
+	}
+
+	let load = || pending.into_par_iter()
+		.map(|(path, file_name, modified, reused)| {
+			let name = path.file_stem().unwrap().to_string_lossy();
+			let could_match = prefilter.is_empty() || prefilter.iter().any(|query| query.could_match_name(&name));
+			let manifest = match reused {
+				Some(manifest) => Ok(Some((manifest, false, could_match))),
+				None => match read_manifest(&path) {
+					Ok(content) => {
+						let could_match = could_match && (prefilter.is_empty() || {
+							let content = decode(&content);
+							prefilter.iter().any(|query| query.could_match_content(&name, &content))
+						});
+						match parse_manifest(&content) {
+							Ok(manifest) => Ok(Some((manifest, true, could_match))),
+							Err(_) if !looks_like_manifest(&content) => Ok(None),
+							Err(source) => Err(Error::ManifestParse { path: path.clone(), source }),
+						}
+					},
+					Err(source) => Err(Error::ManifestRead { path: path.clone(), source }),
+				},
+			};
+			(path, file_name, modified, manifest)
+		})
+		.collect::<Vec<_>>();
+	let loaded = match pool {
+		Some(pool) => pool.install(load),
+		None => load(),
+	};
+
+	for (path, file_name, modified, manifest) in loaded {
+		let (manifest, could_match) = match manifest {
+			Ok(Some((manifest, _, _))) if !manifest.is_app() => {
+				stats.not_apps.push(path);
+				continue
+			},
+			Ok(Some((manifest, new, could_match))) => {
+				parsed += new as usize;
+				(manifest, could_match)
+			},
+			Ok(None) => continue,
+			Err(e) => {
+				stats.failed.push(e);
+				continue
+			},
+		};
+		stats.manifests += 1;
+		if let Some(modified) = modified.filter(|_| cached.is_some()) {
+			fresh.insert(file_name, CachedManifest { modified, manifest: manifest.clone() });
+		}
+		if !could_match {
+			continue
+		}
+
+		let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+		if each(name, manifest, modified).is_break() {
+			stopped = true;
+			stats.limited = true;
+			break
+		}
+	}
+
+	if let (Some(cache), Some(cached)) = (cache, cached) {
+		/* Whatever is left was either not reached or has been deleted. */
+		let changed = parsed > 0 || !stopped && !cached.is_empty();
+		if stopped {
+			fresh.extend(cached);
+		}
+		cache.put_bucket(&base, fresh, changed);
+	}
+	stats.parsed += parsed;
+	Ok(())
+}
+
+/* Bucket names and the directories holding their manifests. Entries that are
+ * not buckets, or fail to be listed, go into `warnings`. */
+fn list_buckets(root: &Path, include: &[String], exclude: &[String], warnings: &mut Vec<Error>) -> Result<Vec<(String, PathBuf)>> {
+	let buckets_base = root.join("buckets");
+	let mut buckets = Vec::new();
+
+	for base in buckets_base.read_dir()
+		.map_err(|source| Error::BucketsDirMissing { path: buckets_base.clone(), source })?
+	{
+		match base {
+			Ok(base) => {
+				let path = base.path();
+				let name = path.file_name().unwrap().to_string_lossy().into_owned();
+				if exclude.contains(&name) || !include.is_empty() && !include.contains(&name) {
+					continue
+				}
+				/* Buckets may be links to a checkout elsewhere, which `is_dir`
+				 * follows; anything else here, or a link to nowhere, is not
+				 * a bucket. */
+				if !path.is_dir() {
+					if path.is_symlink() {
+						warnings.push(Error::BucketLinkDangling(name));
+					}
+					continue
+				}
+				let separate = path.join("bucket");
+				buckets.push((name, if separate.exists() {
+					separate
+				} else {
+					path
+				}));
+			},
+			Err(source) => {
+				warnings.push(Error::BucketsDirMissing { path: buckets_base.clone(), source });
+				continue
+			}
+		}
+	}
+
+	/* In whatever order the file system lists them otherwise. */
+	buckets.sort();
+	Ok(buckets)
+}
+
+/* Buckets of all `roots` the query searches, each with the root it is in. A
+ * bucket reachable from several roots is only searched once, and buckets
+ * sharing a name across roots are told apart by their root. Those asked for
+ * but missing go into `warnings`. */
+fn root_buckets<'a>(roots: &'a [PathBuf], query: &Query, warnings: &mut Vec<Error>) -> Result<Vec<(String, PathBuf, &'a Path)>> {
+	let mut seen = HashSet::new();
+	let mut buckets = Vec::new();
+	for root in roots {
+		for (name, path) in list_buckets(root, &query.buckets, &query.exclude_buckets, warnings)? {
+			if seen.insert(fs::canonicalize(&path).unwrap_or_else(|_| path.clone())) {
+				buckets.push((name, path, root.as_path()));
+			}
+		}
+	}
+
+	/* Looking at the directories themselves, as buckets also excluded are
+	 * left out of the listing without being missing. */
+	for bucket in &query.buckets {
+		if !roots.iter().any(|root| root.join("buckets").join(bucket).is_dir()) {
+			warnings.push(Error::BucketNotFound(bucket.clone()));
+		}
+	}
+
+	let mut counts = HashMap::<String, usize>::new();
+	for (name, ..) in &buckets {
+		*counts.entry(name.clone()).or_default() += 1;
+	}
+	for (name, _, root) in &mut buckets {
+		if counts[name.as_str()] > 1 {
+			*name = format!("{name} ({})", root.display());
+		}
+	}
+	Ok(buckets)
+}
+
+/// Names of all buckets in `roots`, sorted and without duplicates.
+pub fn bucket_names(roots: &[PathBuf]) -> Result<Vec<String>> {
+	let mut names = Vec::new();
+	for root in roots {
+		names.extend(list_buckets(root, &[], &[], &mut Vec::new())?.into_iter().map(|(name, _)| name));
+	}
+	names.sort();
+	names.dedup();
+	Ok(names)
+}
+
+/// Names of apps in the buckets `query` searches starting with `prefix` in
+/// any case, sorted and without duplicates, for completing them in shells.
+/// Only file names are read, manifests are not parsed.
+pub fn complete_names(roots: &[PathBuf], query: &Query, prefix: &str) -> Result<Vec<String>> {
+	let prefix = prefix.to_lowercase();
+	let mut names = Vec::new();
+	for (_, base, _) in root_buckets(roots, query, &mut Vec::new())? {
+		for (path, _, _) in manifest_files(&base, query.recursive)?.files {
+			let name = path.file_stem().unwrap().to_string_lossy();
+			if name.to_lowercase().starts_with(&prefix) {
+				names.push(name.into_owned());
+			}
+		}
+	}
+	names.sort_by(|a, b| natural_cmp(a, b));
+	names.dedup();
+	Ok(names)
+}
+
+/* The URL of the `origin` remote, or else the first one, in a git config. */
+fn remote_url(config: &str) -> Option<String> {
+	let mut remotes = Vec::new();
+	let mut remote = None;
+	for line in config.lines().map(str::trim) {
+		if let Some(section) = line.strip_prefix('[') {
+			remote = section.strip_prefix("remote \"")
+				.and_then(|section| section.strip_suffix("\"]"))
+				.map(str::to_string);
+		} else if let Some(name) = &remote {
+			if let Some((key, value)) = line.split_once('=') {
+				if key.trim() == "url" {
+					remotes.push((name.clone(), value.trim().to_string()));
+				}
+			}
+		}
+	}
+	let origin = remotes.iter().position(|(name, _)| name == "origin").unwrap_or(0);
+	(!remotes.is_empty()).then(|| remotes.swap_remove(origin).1)
+}
+
+/// Git repositories of the buckets `query` searches, by the bucket names
+/// search results use. Buckets that are not git repositories are left out.
+pub fn bucket_repositories(roots: &[PathBuf], query: &Query) -> Result<Vec<(String, PathBuf)>> {
+	let mut repositories = Vec::new();
+	for (name, path, root) in root_buckets(roots, query, &mut Vec::new())? {
+		let buckets_base = root.join("buckets");
+		/* Manifests may be in a `bucket` subdirectory of the repository. */
+		let repository = path.ancestors()
+			.take_while(|dir| *dir != buckets_base)
+			.find(|dir| dir.join(".git").exists());
+		if let Some(repository) = repository {
+			repositories.push((name, repository.to_owned()));
+		}
+	}
+	Ok(repositories)
+}
+
+/// Git remote URLs of the buckets `query` searches, like
+/// [`bucket_repositories`].
+pub fn bucket_remotes(roots: &[PathBuf], query: &Query) -> Result<HashMap<String, String>> {
+	Ok(bucket_repositories(roots, query)?
+		.into_iter()
+		.filter_map(|(name, repository)| {
+			let config = fs::read_to_string(repository.join(".git").join("config")).ok()?;
+			Some((name, remote_url(&config)?))
+		})
+		.collect())
+}
+
+/* Each root has a cache of its own, loaded when reaching its first bucket. */
+fn switch_cache<'a>(cache: &mut Option<(&'a Path, Cache)>, root: &'a Path) {
+	if cache.as_ref().is_none_or(|(cached, _)| *cached != root) {
+		save_cache(cache.take());
+		*cache = Some((root, Cache::load(&Cache::path(root))));
+	}
+}
+
+fn save_cache(cache: Option<(&Path, Cache)>) {
+	if let Some((root, cache)) = cache {
+		if let Err(e) = cache.save(&Cache::path(root)) {
+			eprintln!("Failed to save cache: {e:?}");
+		}
+	}
+}
+
+/// Bring the cache of parsed manifests under `root` up to date, or build it
+/// from scratch if `rebuild`.
+pub fn update_cache(root: &Path, rebuild: bool) -> Result<Stats> {
+	let path = Cache::path(root);
+	let mut cache = if rebuild {
+		Cache::default()
+	} else {
+		Cache::load(&path)
+	};
+	let mut stats = Stats::default();
+	for (_, base) in list_buckets(root, &[], &[], &mut stats.warnings)? {
+		walk_manifests(manifest_files(&base, false)?, Some(&mut cache), &[], None, &mut stats, |_, _, _| ControlFlow::Continue(()))?;
+	}
+	if rebuild {
+		cache.mark_dirty();
+	}
+	cache.save(&path)?;
+	Ok(stats)
+}
+
+/// Search every bucket under the scoop root `root` for a case-insensitive
+/// substring `term`, returning the name and matching entries of each bucket
+/// that has any.
+pub fn search(root: &Path, term: &str) -> Result<BucketResults> {
+	let mut results = BucketResults::new();
+	for found in search_iter(root, term) {
+		let (bucket, entry) = found?;
+		match results.last_mut() {
+			Some((last, entries)) if *last == bucket => entries.push(entry),
+			_ => results.push((bucket, vec![entry])),
+		}
+	}
+	Ok(results)
+}
+
+/// Like [`search`], but with an arbitrary [`Query`].
+pub fn search_with(root: &Path, query: &Query) -> Result<BucketResults> {
+	search_roots(&[root.to_owned()], query)
+}
+
+/// Like [`search_with`], but across several scoop roots, see [`scoop_roots`].
+pub fn search_roots(roots: &[PathBuf], query: &Query) -> Result<BucketResults> {
+	search_stats(roots, query).map(|(results, _)| results)
+}
+
+/// Like [`search_roots`], also counting what the search went through.
+pub fn search_stats(roots: &[PathBuf], query: &Query) -> Result<(BucketResults, Stats)> {
+	let (mut results, stats) = search_batch(roots, std::slice::from_ref(query))?;
+	Ok((results.pop().unwrap_or_default(), stats))
+}
+
+/// Like [`search_stats`], but for several queries at once, walking buckets
+/// only once. Buckets and everything but matching and filtering results are
+/// up to the first query. Returns the results of each query.
+pub fn search_batch(roots: &[PathBuf], queries: &[Query]) -> Result<(Vec<BucketResults>, Stats)> {
+	search_batch_progress(roots, queries, |_, _, _| ())
+}
+
+/// Like [`search_batch`], telling `progress` the name of each bucket before
+/// walking it, along with how many were walked before and in total.
+pub fn search_batch_progress(
+	roots: &[PathBuf],
+	queries: &[Query],
+	progress: impl FnMut(&str, usize, usize),
+) -> Result<(Vec<BucketResults>, Stats)> {
+	let (buckets, warnings) = scoop_buckets(roots, queries)?;
+	collect_results(buckets, warnings, queries, progress)
+}
+
+/// Like [`search_batch`], but searching the buckets at `dirs` rather than
+/// those of scoop roots, like checkouts of bucket repositories, each named
+/// after its directory. Nothing is cached, nor told to be installed.
+pub fn search_dirs(dirs: &[PathBuf], queries: &[Query]) -> Result<(Vec<BucketResults>, Stats)> {
+	let buckets = dirs.iter()
+		.map(|dir| {
+			let (name, base) = bucket_dir(dir);
+			(name, base, None)
+		})
+		.collect();
+	collect_results(buckets, Vec::new(), queries, |_, _, _| ())
+}
+
+fn collect_results(
+	buckets: Buckets<'_>,
+	warnings: Vec<Error>,
+	queries: &[Query],
+	progress: impl FnMut(&str, usize, usize),
+) -> Result<(Vec<BucketResults>, Stats)> {
+	let mut results = queries.iter().map(|_| BucketResults::new()).collect::<Vec<_>>();
+	let stats = search_all(buckets, warnings, queries, progress, |i, bucket, entry| match results[i].last_mut() {
+		Some((last, entries)) if last == bucket => entries.push(entry),
+		_ => results[i].push((bucket.to_string(), vec![entry])),
+	})?;
+
+	for (results, query) in results.iter_mut().zip(queries) {
+		sort_results(results, query);
+	}
+	Ok((results, stats))
+}
+
+fn sort_results(results: &mut BucketResults, query: &Query) {
+	for (_, entries) in results.iter_mut() {
+		sort_entries(entries, query);
+	}
+	if query.sort == Some(SortBy::Bucket) {
+		results.sort_by(|a, b| a.0.cmp(&b.0));
+		if query.reverse {
+			results.reverse();
+		}
+	}
+}
+
+struct Indexed {
+	bucket: String,
+	root: PathBuf,
+	name: String,
+	manifest: Manifest,
+	modified: Option<SystemTime>,
+}
+
+/// Manifests of the buckets a query searches, loaded once to match many
+/// queries against, like while typing them. See [`load_index`].
+pub struct Index {
+	manifests: Vec<Indexed>,
+}
+
+impl Index {
+	/// Like [`search_roots`], but matching the loaded manifests only. Which
+	/// buckets to search and how to walk them are up to the query the index
+	/// was loaded with.
+	pub fn search(&self, query: &Query) -> BucketResults {
+		let mut results = BucketResults::new();
+		let mut unversioned = Vec::new();
+		let mut found = 0;
+		for indexed in &self.manifests {
+			if query.limit.is_some_and(|limit| found >= limit) {
+				break
+			}
+			if !query.could_match_name(&indexed.name) {
+				continue
+			}
+			let Some(entry) = accept_manifest(
+				query, indexed.name.clone(), indexed.manifest.clone(), indexed.modified, Some(&indexed.root), &mut unversioned,
+			) else {
+				continue
+			};
+			found += 1;
+			match results.last_mut() {
+				Some((last, entries)) if *last == indexed.bucket => entries.push(entry),
+				_ => results.push((indexed.bucket.clone(), vec![entry])),
+			}
+		}
+		sort_results(&mut results, query);
+		results
+	}
+}
+
+/// Load the manifests of every bucket `query` searches, for [`Index::search`].
+pub fn load_index(roots: &[PathBuf], query: &Query) -> Result<(Index, Stats)> {
+	let pool = rayon::ThreadPoolBuilder::new()
+		.num_threads(query.jobs.unwrap_or(0))
+		.build()?;
+	let mut cache = None;
+	let mut stats = Stats::default();
+	let mut manifests = Vec::new();
+	for (bucket, path, root) in root_buckets(roots, query, &mut stats.warnings)? {
+		if query.cache {
+			switch_cache(&mut cache, root);
+		}
+		let start = Instant::now();
+		let cache = cache.as_mut().map(|(_, cache)| cache);
+		walk_manifests(manifest_files(&path, query.recursive)?, cache, &[], Some(&pool), &mut stats, |name, manifest, modified| {
+			manifests.push(Indexed { bucket: bucket.clone(), root: root.to_owned(), name, manifest, modified });
+			ControlFlow::Continue(())
+		})?;
+		stats.buckets += 1;
+		stats.scanned.push(bucket);
+		stats.elapsed += start.elapsed();
+	}
+	save_cache(cache);
+	Ok((Index { manifests }, stats))
+}
+
+/// Like [`search_stats`], but handing each entry and the name of its bucket
+/// to `each` as soon as it is found, bucket by bucket, without sorting.
+pub fn search_each(roots: &[PathBuf], query: &Query, mut each: impl FnMut(&str, FindEntry)) -> Result<Stats> {
+	let queries = std::slice::from_ref(query);
+	let (buckets, warnings) = scoop_buckets(roots, queries)?;
+	search_all(buckets, warnings, queries, |_, _, _| (), |_, bucket, entry| each(bucket, entry))
+}
+
+/// Like [`search`], but yielding entries and the names of their buckets one by
+/// one, walking a bucket only once the entries of the one before it are
+/// taken, so that stopping early skips the rest.
+pub fn search_iter(root: &Path, term: &str) -> SearchIter {
+	search_iter_with(vec![root.to_owned()], Query::new(Matcher::substring(term, false)))
+}
+
+/// Like [`search_iter`], but with an arbitrary [`Query`] across several scoop
+/// roots. Entries are sorted within each bucket, but buckets are never.
+pub fn search_iter_with(roots: Vec<PathBuf>, query: Query) -> SearchIter {
+	let mut stats = Stats::default();
+	let (buckets, error) = match root_buckets(&roots, &query, &mut stats.warnings) {
+		Ok(buckets) => (buckets.into_iter().map(|(bucket, path, root)| (bucket, path, root.to_owned())).collect(), None),
+		Err(e) => (Vec::new(), Some(e)),
+	};
+	SearchIter {
+		remaining: query.limit,
+		query,
+		buckets: buckets.into_iter(),
+		bucket: String::new(),
+		pending: Vec::new().into_iter(),
+		error,
+		pool: None,
+		cache: None,
+		stats,
+	}
+}
+
+/// Iterator returned by [`search_iter`]. The cache, if used, is saved when it
+/// is dropped.
+pub struct SearchIter {
+	query: Query,
+	buckets: std::vec::IntoIter<(String, PathBuf, PathBuf)>,
+	bucket: String,
+	pending: std::vec::IntoIter<FindEntry>,
+	remaining: Option<usize>,
+	error: Option<Error>,
+	pool: Option<ThreadPool>,
+	cache: Option<(PathBuf, Cache)>,
+	stats: Stats,
+}
+
+impl SearchIter {
+	/// What the search went through so far.
+	pub fn stats(&self) -> &Stats {
+		&self.stats
+	}
+}
+
+impl Iterator for SearchIter {
+	type Item = Result<(String, FindEntry)>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			if let Some(e) = self.error.take() {
+				return Some(Err(e))
+			}
+			if let Some(entry) = self.pending.next() {
+				return Some(Ok((self.bucket.clone(), entry)))
+			}
+			if self.remaining == Some(0) {
+				return None
+			}
+			let (bucket, path, root) = self.buckets.next()?;
+
+			if self.pool.is_none() {
+				let pool = rayon::ThreadPoolBuilder::new()
+					.num_threads(self.query.jobs.unwrap_or(0))
+					.build();
+				match pool {
+					Ok(pool) => self.pool = Some(pool),
+					Err(e) => return Some(Err(e.into())),
+				}
+			}
+			if self.query.cache && self.cache.as_ref().is_none_or(|(cached, _)| *cached != root) {
+				if let Some((cached, cache)) = self.cache.take() {
+					save_cache(Some((&cached, cache)));
+				}
+				self.cache = Some((root.clone(), Cache::load(&Cache::path(&root))));
+			}
+
+			let mut entries = Vec::new();
+			let remaining = &mut self.remaining;
+			let cache = self.cache.as_mut().map(|(_, cache)| cache);
+			let walked = manifest_files(&path, self.query.recursive).and_then(|listing| walk_matches(
+				listing, std::slice::from_ref(&self.query), cache, Some(&root), self.pool.as_ref(), &mut self.stats,
+				|_, entry| {
+					entries.push(entry);
+					if let Some(remaining) = remaining {
+						*remaining -= 1;
+					}
+					if *remaining == Some(0) {
+						ControlFlow::Break(())
+					} else {
+						ControlFlow::Continue(())
+					}
+				},
+			));
+			if let Err(e) = walked {
+				return Some(Err(e))
+			}
+			sort_entries(&mut entries, &self.query);
+			self.stats.scanned.push(bucket.clone());
+			self.bucket = bucket;
+			self.pending = entries.into_iter();
+		}
+	}
+}
+
+impl Drop for SearchIter {
+	fn drop(&mut self) {
+		if let Some((root, cache)) = self.cache.take() {
+			save_cache(Some((&root, cache)));
+		}
+	}
+}
+
+/* Buckets to walk by name, with the scoop root of each if in one. */
+type Buckets<'a> = Vec<(String, PathBuf, Option<&'a Path>)>;
+
+/* Buckets of `roots` as the first of `queries` searches them, for `search_all`,
+ * along with what it warned of. */
+fn scoop_buckets<'a>(roots: &'a [PathBuf], queries: &[Query]) -> Result<(Buckets<'a>, Vec<Error>)> {
+	let mut warnings = Vec::new();
+	let Some(first) = queries.first() else {
+		return Ok((Vec::new(), warnings))
+	};
+	let buckets = root_buckets(roots, first, &mut warnings)?.into_iter()
+		.map(|(name, path, root)| (name, path, Some(root)))
+		.collect();
+	Ok((buckets, warnings))
+}
+
+/* A bucket at `path` outside of any scoop root, named after its directory. */
+fn bucket_dir(path: &Path) -> (String, PathBuf) {
+	let name = fs::canonicalize(path).ok()
+		.and_then(|path| Some(path.file_name()?.to_string_lossy().into_owned()))
+		.unwrap_or_else(|| path.display().to_string());
+	let separate = path.join("bucket");
+	(name, if separate.exists() { separate } else { path.to_owned() })
+}
+
+/* Walk `buckets` for matches of `queries`. Buckets outside of scoop roots
+ * are never cached. */
+fn search_all(
+	buckets: Buckets<'_>,
+	warnings: Vec<Error>,
+	queries: &[Query],
+	mut progress: impl FnMut(&str, usize, usize),
+	mut each: impl FnMut(usize, &str, FindEntry),
+) -> Result<Stats> {
+	let mut stats = Stats { warnings, ..Stats::default() };
+	let Some(first) = queries.first() else {
+		return Ok(stats)
+	};
+	let pool = rayon::ThreadPoolBuilder::new()
+		.num_threads(first.jobs.unwrap_or(0))
+		.build()
+		?;
+	let mut cache = None;
+	let mut remaining = queries.iter().map(|query| query.limit).collect::<Vec<_>>();
+
+	/* Listing a bucket is mostly waiting on the disk, or the network for one on
+	 * a share, so the next few are listed while walking one. They are still
+	 * walked in order, and none are listed past where the walk stops. */
+	let ahead = pool.current_num_threads();
+	let recursive = first.recursive;
+	let list = |path: &Path| {
+		let (sender, receiver) = mpsc::sync_channel(1);
+		let path = path.to_owned();
+		pool.spawn(move || {
+			/* Nobody is waiting for it anymore if the walk stopped. */
+			let _ = sender.send(manifest_files(&path, recursive));
+		});
+		receiver
+	};
+	let mut listings = buckets.iter().take(ahead).map(|(_, path, _)| list(path)).collect::<VecDeque<_>>();
+	let total = buckets.len();
+	for (walked, (bucket, _, root)) in buckets.iter().enumerate() {
+		if remaining.iter().all(|remaining| *remaining == Some(0)) {
+			break
+		}
+		if let Some((_, path, _)) = buckets.get(walked + ahead) {
+			listings.push_back(list(path));
+		}
+		let listing = listings.pop_front().unwrap().recv().unwrap();
+		progress(bucket, walked, total);
+		if let Some(root) = root.filter(|_| first.cache) {
+			switch_cache(&mut cache, root);
+		}
+
+		let cache = cache.as_mut().filter(|_| root.is_some()).map(|(_, cache)| cache);
+		let mut left_out = false;
+		walk_matches(listing?, queries, cache, *root, Some(&pool), &mut stats, |i, entry| {
+			if remaining[i] == Some(0) {
+				left_out = true;
+				return ControlFlow::Continue(())
+			}
+			each(i, bucket, entry);
+			if let Some(remaining) = &mut remaining[i] {
+				*remaining -= 1;
+			}
+			if remaining.iter().all(|remaining| *remaining == Some(0)) {
+				ControlFlow::Break(())
+			} else {
+				ControlFlow::Continue(())
+			}
+		})?;
+		stats.limited |= left_out;
+		stats.scanned.push(bucket.clone());
+	}
+
+	save_cache(cache);
+	Ok(stats)
+}
+
+/// Names of apps in the buckets `query` searches closest to `term` by edit
+/// distance, closest first, for suggesting after a search found nothing.
+/// Only file names are read, manifests are not parsed.
+pub fn similar_names(roots: &[PathBuf], query: &Query, term: &str, count: usize) -> Result<Vec<String>> {
+	let term = term.to_lowercase();
+	/* Anything further away than this is more of a different name than a typo. */
+	let max_distance = (term.chars().count() / 3).max(1);
+	let mut names = Vec::new();
+	/* The search before tells of missing buckets already. */
+	for (_, path, _) in root_buckets(roots, query, &mut Vec::new())? {
+		for (path, _, _) in manifest_files(&path, query.recursive)?.files {
+			let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+			let distance = strsim::osa_distance(&term, &name.to_lowercase());
+			if distance <= max_distance {
+				names.push((distance, name));
+			}
+		}
+	}
+	names.sort();
+	names.dedup_by(|a, b| a.1 == b.1);
+	Ok(names.into_iter().take(count).map(|(_, name)| name).collect())
+}
+
+/// What is wrong with a manifest found by [`validate`].
+#[derive(Debug)]
+pub enum ProblemKind {
+	/// A field scoop or the tests of buckets ask for is missing.
+	MissingField(&'static str),
+	/// A bin has an extension scoop can not make a shim for.
+	NotExecutable(PathBuf),
+}
+
+/// A problem with the manifest of app `app` in bucket `bucket`.
+#[derive(Debug)]
+pub struct Problem {
+	pub bucket: String,
+	pub app: String,
+	pub kind: ProblemKind,
+}
+
+/* What scoop makes shims for. */
+const EXECUTABLE_EXTENSIONS: [&str; 7] = ["exe", "com", "bat", "cmd", "ps1", "jar", "py"];
+
+fn check_manifest(bucket: &str, app: String, manifest: Manifest, problems: &mut Vec<Problem>) {
+	let missing = [
+		("version", manifest.version.is_none()),
+		("description", manifest.description.is_none()),
+		("homepage", manifest.homepage.is_none()),
+		("license", manifest.license.is_none()),
+	];
+	let mut kinds = missing.into_iter()
+		.filter(|(_, missing)| *missing)
+		.map(|(field, _)| ProblemKind::MissingField(field))
+		.collect::<Vec<_>>();
+	let bins = manifest.bin.into_iter()
+		.chain(manifest.architecture.into_iter().flatten().filter_map(|(_, architecture)| architecture.bin))
+		.flat_map(ManifestBinField::into_bins);
+	for (bin, _) in bins {
+		let executable = bin.extension().is_some_and(|ext| {
+			EXECUTABLE_EXTENSIONS.iter().any(|executable| ext.eq_ignore_ascii_case(executable))
+		});
+		if !executable {
+			kinds.push(ProblemKind::NotExecutable(bin));
+		}
+	}
+	problems.extend(kinds.into_iter().map(|kind| Problem { bucket: bucket.to_string(), app: app.clone(), kind }));
+}
+
+/* Manifests are read in parallel, so in no particular order. */
+fn sort_problems(problems: &mut [Problem]) {
+	problems.sort_by(|a, b| a.bucket.cmp(&b.bucket).then_with(|| natural_cmp(&a.app, &b.app)));
+}
+
+fn validate_bucket(bucket: &str, base: &Path, recursive: bool, problems: &mut Vec<Problem>, stats: &mut Stats) -> Result<()> {
+	stats.buckets += 1;
+	stats.scanned.push(bucket.to_string());
+	let before = stats.not_apps.len();
+	walk_manifests(manifest_files(base, recursive)?, None, &[], None, stats, |app, manifest, _| {
+		check_manifest(bucket, app, manifest, problems);
+		ControlFlow::Continue(())
+	})?;
+	/* Searches skip those quietly, but in a bucket they are broken manifests. */
+	for path in &stats.not_apps[before..] {
+		problems.push(Problem {
+			bucket: bucket.to_string(),
+			app: path.file_stem().unwrap().to_string_lossy().into_owned(),
+			kind: ProblemKind::MissingField("version"),
+		});
+	}
+	Ok(())
+}
+
+/// Check every manifest in the buckets `query` searches for problems, like
+/// for linting buckets. Manifests failing to be read or parsed are in
+/// [`Stats::failed`] instead.
+pub fn validate(roots: &[PathBuf], query: &Query) -> Result<(Vec<Problem>, Stats)> {
+	let mut problems = Vec::new();
+	let mut stats = Stats::default();
+	for (bucket, base, _) in root_buckets(roots, query, &mut stats.warnings)? {
+		validate_bucket(&bucket, &base, query.recursive, &mut problems, &mut stats)?;
+	}
+	sort_problems(&mut problems);
+	Ok((problems, stats))
+}
+
+/// Like [`validate`], for a bucket at `path` not necessarily added to scoop,
+/// like a checkout of its repository.
+pub fn validate_dir(path: &Path, recursive: bool) -> Result<(Vec<Problem>, Stats)> {
+	let (bucket, base) = bucket_dir(path);
+	let mut problems = Vec::new();
+	let mut stats = Stats::default();
+	validate_bucket(&bucket, &base, recursive, &mut problems, &mut stats)?;
+	sort_problems(&mut problems);
+	Ok((problems, stats))
+}
+
+#[cfg(test)]
+mod tests {
+	use {
+		std::sync::Mutex,
+		super::*,
+	};
+
+	/* Tests run on several threads, but the environment is shared. */
+	static ENV: Mutex<()> = Mutex::new(());
+
+	/* A fresh directory to test `name` in. */
+	fn temp_dir(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir().join(format!("scoop-searchr-{}-{name}", std::process::id()));
+		let _ = fs::remove_dir_all(&dir);
+		fs::create_dir_all(&dir).unwrap();
+		dir
+	}
+
+	/* Run `f` with environment variables set as in `vars`, or unset if
+	 * `None`, putting them back afterwards. */
+	fn with_env<T>(vars: &[(&str, Option<&Path>)], f: impl FnOnce() -> T) -> T {
+		let _guard = ENV.lock().unwrap_or_else(|e| e.into_inner());
+		let saved = vars.iter().map(|&(key, _)| (key, std::env::var_os(key))).collect::<Vec<_>>();
+		for &(key, value) in vars {
+			match value {
+				Some(value) => std::env::set_var(key, value),
+				None => std::env::remove_var(key),
+			}
+		}
+		let result = f();
+		for (key, value) in saved {
+			match value {
+				Some(value) => std::env::set_var(key, value),
+				None => std::env::remove_var(key),
+			}
+		}
+		result
+	}
+
+	fn write_config(dir: &Path, root_path: &Path) {
+		fs::create_dir_all(dir).unwrap();
+		let config = serde_json::json!({ "root_path": root_path });
+		fs::write(dir.join("config.json"), config.to_string()).unwrap();
+	}
+
+	#[test]
+	fn user_config_wins_over_default_root() {
+		let dir = temp_dir("user-config");
+		let moved = dir.join("moved");
+		fs::create_dir(&moved).unwrap();
+		write_config(&dir.join("xdg").join("scoop"), &moved);
+		let home = with_env(&[("SCOOP", None), ("XDG_CONFIG_HOME", Some(&dir.join("xdg")))], scoop_home);
+		assert_eq!(home.unwrap(), moved);
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn scoop_wins_over_user_config() {
+		let dir = temp_dir("scoop-env");
+		let root = dir.join("scoop");
+		let moved = dir.join("moved");
+		fs::create_dir(&root).unwrap();
+		fs::create_dir(&moved).unwrap();
+		write_config(&dir.join("xdg").join("scoop"), &moved);
+		let home = with_env(&[("SCOOP", Some(&root)), ("XDG_CONFIG_HOME", Some(&dir.join("xdg")))], scoop_home);
+		assert_eq!(home.unwrap(), root);
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn config_under_scoop_wins_over_scoop() {
+		let dir = temp_dir("scoop-config");
+		let root = dir.join("scoop");
+		let moved = dir.join("moved");
+		fs::create_dir(&moved).unwrap();
+		write_config(&root, &moved);
+		let home = with_env(&[("SCOOP", Some(&root))], scoop_home);
+		assert_eq!(home.unwrap(), moved);
+		/* Unless where it points to is gone. */
+		fs::remove_dir(&moved).unwrap();
+		let home = with_env(&[("SCOOP", Some(&root))], scoop_home);
+		assert_eq!(home.unwrap(), root);
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn substring_folds_case_beyond_ascii() {
+		let found = Matcher::substring("привет", false).find_text("Скажи ПРИВЕТ миру").unwrap();
+		assert_eq!(found.spans, [(11, 23)]);
+		let found = Matcher::substring("strasse", false).find_text("Die Straße entlang").unwrap();
+		assert_eq!(found.spans, [(4, 11)]);
+		assert!(Matcher::substring("strasse", true).find_text("Die Straße entlang").is_none());
+	}
+
+	#[test]
+	fn env_path_drops_quotes_and_trailing_separators() {
+		assert_eq!(env_path(r#""C:\x\""#), Path::new(r"C:\x"));
+		assert_eq!(env_path(r"C:\x\"), Path::new(r"C:\x"));
+		assert_eq!(env_path(r"C:\"), Path::new(r"C:\"));
+		assert_eq!(env_path("/"), Path::new("/"));
+		assert_eq!(env_path("/scoop/"), Path::new("/scoop"));
+		assert_eq!(env_path("''"), Path::new(""));
+	}
+
+	const ALIASED: &str = r#"{"version": "1.0", "bin": [["tools/busybox.exe", "bbsh", "--shell"], "busybox.exe"]}"#;
+
+	#[test]
+	fn into_bins_takes_aliases_of_commands() {
+		let bins = parse_manifest(ALIASED.as_bytes()).unwrap().bin.unwrap().into_bins();
+		assert_eq!(bins, [
+			(PathBuf::from("tools/busybox.exe"), Some("bbsh".to_string())),
+			(PathBuf::from("busybox.exe"), None),
+		]);
+	}
+
+	#[test]
+	fn aliases_are_matched_as_bins() {
+		let manifest = parse_manifest(ALIASED.as_bytes()).unwrap();
+		let entry = match_manifest("busybox".to_string(), manifest.clone(), &Query::new(Matcher::substring("bbsh", false))).unwrap();
+		assert_eq!(entry.matched_on, MatchField::Bin);
+		assert_eq!(entry.bins, [PathBuf::from("bbsh")]);
+		assert!(match_manifest("busybox".to_string(), manifest, &Query::new(Matcher::exact("bbsh", false))).is_some());
+	}
+
+	#[test]
+	fn decomposed_and_composed_accents_match() {
+		let composed = "caf\u{e9}";
+		let decomposed = "cafe\u{301}";
+		assert!(Matcher::substring(decomposed, false).find(composed).is_some());
+		let manifest = parse_manifest(br#"{"version": "1.0", "description": "cafe\u0301 au lait"}"#).unwrap();
+		let entry = match_manifest("latte".to_string(), manifest, &Query::new(Matcher::substring(composed, false))).unwrap();
+		assert_eq!(entry.matched_on, MatchField::Description);
+		assert_eq!(entry.description_spans, [(0, composed.len())]);
+	}
+
+	#[test]
+	fn decomposed_file_names_match_by_name_only() {
+		let dir = temp_dir("decomposed-name");
+		fs::create_dir_all(&dir).unwrap();
+		fs::write(dir.join("cafe\u{301}.json"), r#"{"version": "1.0"}"#).unwrap();
+
+		let mut query = Query::new(Matcher::substring("caf\u{e9}", false));
+		query.fields = Fields { name: true, ..Fields::none() };
+		let (results, _) = search_dirs(std::slice::from_ref(&dir), &[query]).unwrap();
+		assert_eq!(results[0].len(), 1);
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn validate_reports_json_that_is_no_app() {
+		let dir = temp_dir("validate-no-app");
+		fs::create_dir_all(&dir).unwrap();
+		fs::write(dir.join("app.json"), r#"{"version": "1.0", "description": "An app", "homepage": "https://example.com", "license": "MIT"}"#).unwrap();
+		fs::write(dir.join("unversioned.json"), r#"{"notes": "no version"}"#).unwrap();
+
+		let (problems, stats) = validate_dir(&dir, false).unwrap();
+		assert_eq!(stats.not_apps, [dir.join("unversioned.json")]);
+		assert!(matches!(&problems[..], [Problem { app, kind: ProblemKind::MissingField("version"), .. }] if app == "unversioned"));
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn linked_buckets_are_searched() {
+		let dir = temp_dir("linked-bucket");
+		let root = dir.join("scoop");
+		let checkout = dir.join("checkout");
+		fs::create_dir_all(root.join("buckets")).unwrap();
+		fs::create_dir_all(checkout.join("bucket")).unwrap();
+		fs::write(checkout.join("bucket").join("linkedapp.json"), r#"{"version": "1.0"}"#).unwrap();
+		std::os::unix::fs::symlink(&checkout, root.join("buckets").join("linked")).unwrap();
+		std::os::unix::fs::symlink(dir.join("gone"), root.join("buckets").join("dangling")).unwrap();
+
+		let (results, stats) = search_stats(&[root], &Query::new(Matcher::substring("linkedapp", false))).unwrap();
+		assert!(matches!(&stats.warnings[..], [Error::BucketLinkDangling(name)] if name == "dangling"));
+		assert_eq!(results.len(), 1);
+		let (bucket, entries) = &results[0];
+		assert_eq!(bucket, "linked");
+		assert_eq!(entries.iter().map(|entry| entry.name.as_str()).collect::<Vec<_>>(), ["linkedapp"]);
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn fold_maps_back_to_original_offsets() {
+		let folded = fold("Straße", false);
+		assert_eq!(folded.text, "strasse");
+		assert_eq!(folded.original(folded.text.len()), "Straße".len());
+	}
+}
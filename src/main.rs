@@ -1,222 +1,1646 @@
 use {
 	std::{
+		collections::{BTreeMap, HashMap, HashSet},
+		ffi::OsString,
 		fs,
+		io::{self, IsTerminal, Write},
+		num::NonZeroUsize,
 		path::{Path, PathBuf},
+		process,
+		sync::{Arc, Mutex, mpsc},
+		thread,
+		time::{Duration, Instant, SystemTime},
 	},
-	serde::Deserialize,
+	serde::Serialize,
 	anyhow::{Context, Result, bail},
+	clap::{
+		Arg, ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum,
+		builder::PossibleValuesParser, parser::ValueSource,
+	},
+	clap_complete::Shell,
+	indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle},
+	notify::{
+		EventKind, RecursiveMode, Watcher,
+		event::{MetadataKind, ModifyKind},
+	},
+	scoop_searchr::{
+		Arch, Error, ExtraMatch, FindEntry, Fields, MatchField, Matcher, NumericVersion, Problem, ProblemKind, Query, SortBy,
+		compare_versions,
+		BucketResults, bucket_names, complete_names, bucket_remotes, bucket_repositories, scoop_roots, searchr_config_path, search_batch_progress, search_dirs, search_iter_with, search_each, similar_names, update_cache, validate, validate_dir,
+	},
 };
 
-#[derive(Deserialize, Debug)]
-struct ScoopConfig {
-	root_path: Option<PathBuf>,
+#[cfg(feature = "color")]
+use owo_colors::OwoColorize;
+#[cfg(feature = "archive")]
+use scoop_searchr::search_archives;
+
+#[cfg(feature = "interactive")]
+mod interactive;
+
+/* Copied from https://github.com/shilangyu/scoop-search/blob/8b6b1809cd5d8d03735d39bc5a16e9556328927d/args.go#L25 */
+const HOOK_PWSH: &str = r#"function scoop { if ($args[0] -eq "search") { scoop-searchr.exe @($args | Select-Object -Skip 1) } else { scoop.ps1 @args } }"#;
+const HOOK_NU: &str = r#"def --wrapped scoop [...args] { if ($args | length) > 0 and $args.0 == "search" { ^scoop-searchr.exe ...($args | skip 1) } else { ^scoop ...$args } }"#;
+/* doskey macros can not shift their arguments, so pass on the most cmd.exe
+ * users would type. */
+const HOOK_CMD: &str = r#"doskey scoop=if "$1"=="search" (scoop-searchr.exe $2 $3 $4 $5 $6 $7 $8 $9) else (scoop.cmd $*)"#;
+
+/* Render `text` with the byte ranges in `spans` highlighted. */
+fn highlight(text: &str, spans: &[(usize, usize)], color: bool) -> String {
+	if !color {
+		return text.to_string()
+	}
+	#[cfg(feature = "color")]
+	let paint = |text: &str| text.red().bold().to_string();
+	#[cfg(not(feature = "color"))]
+	let paint = |text: &str| text.to_string();
+	let mut rendered = String::new();
+	let mut last = 0;
+	for &(start, end) in spans {
+		rendered += &text[last..start];
+		rendered += &paint(&text[start..end]);
+		last = end;
+	}
+	rendered += &text[last..];
+	rendered
 }
 
-#[derive(Deserialize, Debug)]
-#[serde(untagged)]
-enum ManifestBinItem {
-	Path(PathBuf),
-	Command(Vec<String>),
+/* Columns `line` takes up in a terminal, skipping color escape sequences. */
+fn visible_width(line: &str) -> usize {
+	let mut width = 0;
+	let mut chars = line.chars();
+	while let Some(c) = chars.next() {
+		match c {
+			'\x1b' => {
+				chars.by_ref().find(|c| c.is_ascii_alphabetic());
+			},
+			'\t' => width += 8 - width % 8,
+			_ => width += 1,
+		}
+	}
+	width
 }
 
-#[derive(Deserialize, Debug)]
-#[serde(untagged)]
-enum ManifestBinField {
-	Path(PathBuf),
-	PathOrCommandList(Vec<ManifestBinItem>),
+/* Cut `text` to at most `max` characters, ending with an ellipsis if cut, and
+ * `spans` along with it. */
+fn truncate(text: String, spans: Vec<(usize, usize)>, max: usize) -> (String, Vec<(usize, usize)>) {
+	if text.chars().count() <= max {
+		return (text, spans)
+	}
+	let cut = text.char_indices().nth(max.saturating_sub(1)).map_or(text.len(), |(i, _)| i);
+	let spans = spans.into_iter()
+		.filter(|&(start, _)| start < cut)
+		.map(|(start, end)| (start, end.min(cut)))
+		.collect();
+	(format!("{}…", &text[..cut]), spans)
 }
 
-#[derive(Deserialize, Debug)]
-struct Manifest {
-	version: String,
-	bin: Option<ManifestBinField>,
-	description: Option<String>,
+/* `count` and `noun`, plural unless there is one. */
+fn counted(count: usize, noun: &str) -> String {
+	if count == 1 {
+		format!("{count} {noun}")
+	} else {
+		format!("{count} {noun}s")
+	}
 }
 
-fn scoop_home() -> Result<PathBuf> {
-	if let Ok(env_var) = std::env::var("SCOOP") {
-		let env_path = PathBuf::from(env_var);
-		if !env_path.exists() {
-			bail!("The SCOOP environment variable is set ({env_path:?}) but it does not exist");
+fn print_skipped(failed: &[Error]) {
+	if failed.is_empty() {
+		return
+	}
+	eprintln!("{} skipped:", counted(failed.len(), "manifest"));
+	for e in failed {
+		match std::error::Error::source(e) {
+			Some(source) => eprintln!("	{e}: {source}"),
+			None => eprintln!("	{e}"),
 		}
-		Ok(env_path)
-	} else {
-		let user_home = directories::UserDirs::new()
-			.context("can not locate user home directory")?
-			.home_dir()
-			.to_owned();
-		let config_home = std::env::var("XDG_CONFIG_HOME")
-			.map_or(user_home.join(".config"), PathBuf::from);
-		let config_json = config_home.join("scoop").join("config.json");
-		if let Ok(file) = fs::File::open(config_json) {
-			if let Ok(ScoopConfig{ root_path: Some(root_path) }) = serde_json::from_reader(&file) {
-				return Ok(root_path)
-			}
-		}
-		let default = user_home.join("scoop");
-		if default.exists() {
-			Ok(default)
-		} else {
-			bail!("failed to fall back to default location, does not exist")
+	}
+}
+
+/* What searches left out other than manifests, see `Stats::warnings`. */
+fn print_warnings(warnings: &[Error]) {
+	for e in warnings {
+		match std::error::Error::source(e) {
+			Some(source) => eprintln!("Skipping, {e}: {source}"),
+			None => eprintln!("Skipping, {e}"),
 		}
 	}
 }
 
-#[derive(PartialEq, PartialOrd, Eq, Ord)]
-struct FindEntry {
-	name: String,
-	version: String,
-	bin: Option<PathBuf>,
-	description: Option<String>,
+/* `time` as a UTC date like `2024-01-05`. */
+fn date(time: SystemTime) -> String {
+	let days = time.duration_since(SystemTime::UNIX_EPOCH).map_or(0, |since| since.as_secs() / 86400);
+	/* Days to civil dates, per https://howardhinnant.github.io/date_algorithms.html#civil_from_days */
+	let days = days + 719468;
+	let era = days / 146097;
+	let day_of_era = days % 146097;
+	let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let shifted_month = (5 * day_of_year + 2) / 153;
+	let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+	let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+	let year = year_of_era + era * 400 + (month <= 2) as u64;
+	format!("{year:04}-{month:02}-{day:02}")
 }
 
-fn find_manifests(base: &Path, term: &str) -> Result<Vec<FindEntry>> {
-	let term = term.to_lowercase();
-	let walk = base.read_dir()
-		.with_context(|| format!("failed to list manifests in {base:?}"))?;
-	let mut results = Vec::new();
+/* Options set in the config file as arguments, leaving out those given on
+ * the command line or conflicting with any given there, which take
+ * precedence. */
+/* Set for the searches `--watch` runs, which must neither watch again nor
+ * update buckets each time, whatever the config says. */
+const WATCH_CHILD: &str = "SEARCHR_WATCH_CHILD";
 
-	for maybe_entry in walk {
-		let path = match maybe_entry {
-			Ok(entry) => entry.path(),
-			Err(e) => {
-				eprintln!("Error walking directory {base:?}: {e:?}");
-				continue
-			},
+fn config_args(command: &clap::Command, given: &ArgMatches) -> Vec<String> {
+	let Some(path) = searchr_config_path() else {
+		return Vec::new()
+	};
+	let Ok(content) = fs::read_to_string(&path) else {
+		return Vec::new()
+	};
+	let table = match content.parse::<toml::Table>() {
+		Ok(table) => table,
+		Err(e) => {
+			eprintln!("Failed to parse config {path:?}, ignoring it: {e}");
+			return Vec::new()
+		},
+	};
+	let on_command_line = |arg: &Arg| given.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine);
+	let mut args = Vec::new();
+	for (key, value) in table {
+		let Some(arg) = command.get_arguments().find(|arg| arg.get_long() == Some(key.as_str())) else {
+			eprintln!("Unknown option {key:?} in config {path:?}, ignoring it");
+			continue
 		};
-
-		if path.extension().map(|ext| ext.to_str()) != Some(Some("json")) {
+		if ["watch", "update"].contains(&key.as_str()) && std::env::var_os(WATCH_CHILD).is_some() {
 			continue
 		}
+		/* Conflicts are declared on either of the two args only. */
+		let conflicts = |a: &Arg, b: &Arg| command.get_arg_conflicts_with(a).into_iter().any(|c| c.get_id() == b.get_id());
+		if on_command_line(arg) || command.get_arguments()
+			.filter(|&other| on_command_line(other))
+			.any(|other| conflicts(arg, other) || conflicts(other, arg))
+		{
+			continue
+		}
+		let values = match value {
+			toml::Value::Array(values) => values,
+			value => vec![value],
+		};
+		for value in values {
+			match value {
+				toml::Value::Boolean(true) => args.push(format!("--{key}")),
+				toml::Value::Boolean(false) => (),
+				toml::Value::String(value) => args.push(format!("--{key}={value}")),
+				value => args.push(format!("--{key}={value}")),
+			}
+		}
+	}
+	args
+}
 
-		let manifest = match fs::read(&path) {
-			Ok(content) => match serde_json::from_slice::<Manifest>(&content) {
-				Ok(manifest) => manifest,
-				Err(e) => {
-					eprintln!("Failed to parse manifest at {path:?}: {e:?}");
-					continue
-				}
-			},
-			Err(e) => {
-				eprintln!("Failed to read manifest at {path:?}: {e:?}");
-				continue
+/* A hidden `--no-<flag>` for each flag, overriding it, so that one set in the
+ * config can be turned off again on the command line. */
+fn with_negations(command: clap::Command) -> clap::Command {
+	let negations = command.get_arguments()
+		.filter(|arg| matches!(arg.get_action(), ArgAction::SetTrue))
+		.filter_map(|arg| Some((arg.get_id().clone(), format!("no-{}", arg.get_long()?))))
+		.filter(|(_, negation)| command.get_arguments().all(|arg| arg.get_long() != Some(negation.as_str())))
+		.collect::<Vec<_>>();
+	negations.into_iter().fold(command, |command, (id, negation)| command.arg(
+		Arg::new(negation.clone()).long(negation).action(ArgAction::SetTrue).overrides_with(id).hide(true)
+	))
+}
+
+/* `git pull` each of `repositories` at once, telling which changed if
+ * `report`. Failing to pull one is no reason not to search the rest. */
+fn update_buckets(repositories: &[(String, PathBuf)], report: bool) {
+	let git = |repository: &PathBuf, args: &[&str]| {
+		process::Command::new("git").arg("-C").arg(repository).args(args).output()
+	};
+	let head = |repository| git(repository, &["rev-parse", "HEAD"]).ok().map(|output| output.stdout);
+	thread::scope(|scope| {
+		let pulls = repositories.iter()
+			.map(|(bucket, repository)| scope.spawn(move || {
+				let before = head(repository);
+				let pulled = git(repository, &["pull", "--ff-only", "--quiet"]);
+				(bucket, pulled, before != head(repository))
+			}))
+			.collect::<Vec<_>>();
+		for pull in pulls {
+			let (bucket, pulled, changed) = pull.join().unwrap();
+			match pulled {
+				Ok(output) if output.status.success() => {
+					if report && changed {
+						eprintln!("Updated bucket '{bucket}'");
+					}
+				},
+				Ok(output) => eprintln!(
+					"Failed to update bucket '{bucket}': {}",
+					String::from_utf8_lossy(&output.stderr).trim(),
+				),
+				Err(e) => eprintln!("Failed to update bucket '{bucket}': {e}"),
 			}
-		};
+		}
+	});
+}
 
-		let name = path.file_stem().unwrap().to_string_lossy().into_owned();
-		if name.contains(&term) {
-			results.push(FindEntry {
-				name,
-				version: manifest.version,
-				bin: None,
-				description: None,
-			});
+/* Run ourselves without `--watch` anew, on a cleared screen, whenever a manifest
+ * under any of `roots` changes. */
+fn watch(roots: &[PathBuf]) -> Result<()> {
+	let exe = std::env::current_exe().context("failed to locate own executable")?;
+	let mut separated = false;
+	let args = std::env::args_os()
+		.skip(1)
+		.filter(|arg| {
+			separated |= arg == "--";
+			separated || arg != "--watch" && arg != "--update"
+		})
+		.collect::<Vec<_>>();
+	let search = || -> Result<()> {
+		print!("\x1b[2J\x1b[H");
+		io::stdout().flush()?;
+		process::Command::new(&exe).args(&args).env(WATCH_CHILD, "1").status().context("failed to search")?;
+		Ok(())
+	};
+
+	let (sender, receiver) = mpsc::channel();
+	let mut watcher = notify::recommended_watcher(sender).context("failed to watch buckets")?;
+	for root in roots {
+		let buckets = root.join("buckets");
+		watcher.watch(&buckets, RecursiveMode::Recursive)
+			.with_context(|| format!("failed to watch {buckets:?}"))?;
+	}
+	search()?;
+	let is_manifest = |path: &PathBuf| path.extension().is_some_and(|extension| extension == "json");
+	for event in &receiver {
+		/* Searching reads manifests, which is no change. */
+		let changed = event.is_ok_and(|event| match event.kind {
+			EventKind::Access(_) | EventKind::Modify(ModifyKind::Metadata(MetadataKind::AccessTime)) => false,
+			_ => event.paths.iter().any(is_manifest),
+		});
+		if !changed {
 			continue
 		}
+		/* Wait for changes to settle, so a `git pull` updating many manifests
+		 * searches only once. */
+		while receiver.recv_timeout(Duration::from_millis(300)).is_ok() {}
+		search()?;
+	}
+	Ok(())
+}
 
-		if let Some(bin_field) = manifest.bin {
-			let bins = match bin_field {
-				ManifestBinField::Path(path) => vec![path],
-				ManifestBinField::PathOrCommandList(list) => list
-					.into_iter()
-					.filter_map(|item| match item {
-						ManifestBinItem::Command(command) => command.first().map(PathBuf::from),
-						ManifestBinItem::Path(path) => Some(path),
-					})
-					.collect(),
-			};
-			if let Some(bin_path) = bins.into_iter().find(|bin| bin.file_stem()
-				.unwrap()
-				.to_string_lossy()
-				.to_lowercase()
-				.contains(&term)
-			) {
-				results.push(FindEntry {
-					name,
-					version: manifest.version,
-					bin: Some(bin_path),
-					description: None,
-				});
-				continue
+/* Longer than any name or description worth searching for. */
+const MAX_TERM_LEN: usize = 256;
+
+/* Refuse terms that are more likely binary data or a whole file piped in by
+ * mistake than something to search for, instead of matching them against
+ * every manifest. */
+fn check_term(term: &str) -> Result<()> {
+	let len = term.chars().count();
+	if len > MAX_TERM_LEN {
+		bail!("term is {len} characters long, at most {MAX_TERM_LEN} are searched for");
+	}
+	if term.chars().any(char::is_control) {
+		bail!("term {:?} contains control characters, is it binary data?", term.chars().take(32).collect::<String>());
+	}
+	Ok(())
+}
+
+/* Split terms into those to match and those to exclude. Without any to match,
+ * like with `--all`, everything does, whichever way terms are matched. */
+fn split_terms(raw: &[String]) -> (Vec<&str>, Vec<&str>) {
+	let mut terms = Vec::new();
+	let mut excludes = Vec::new();
+	for term in raw {
+		if let Some(term) = term.strip_prefix('\\').filter(|term| term.starts_with(['-', '!'])) {
+			terms.push(term);
+		} else if let Some(term) = term.strip_prefix(['-', '!']).filter(|term| !term.is_empty()) {
+			excludes.push(term);
+		} else {
+			terms.push(term);
+		}
+	}
+	(terms, excludes)
+}
+
+/* How to show a version manifests may leave out. */
+fn shown_version(version: &Option<String>) -> &str {
+	version.as_deref().unwrap_or("unknown")
+}
+
+/* Results by app, whatever the case of its name in each bucket, along with
+ * the buckets providing it. */
+fn by_app(results: BucketResults) -> Vec<Vec<(String, FindEntry)>> {
+	let mut apps = BTreeMap::<String, Vec<(String, FindEntry)>>::new();
+	for (bucket, entries) in results {
+		for entry in entries {
+			apps.entry(entry.name.to_lowercase()).or_default().push((bucket.clone(), entry));
+		}
+	}
+	apps.into_values().collect()
+}
+
+/* Entries of the same name in different buckets together, by name, each
+ * with the highest version first. */
+fn dedup(results: BucketResults) -> Vec<Vec<(String, FindEntry)>> {
+	by_app(results).into_iter()
+		.map(|mut found| {
+			found.sort_by(|(_, a), (_, b)| compare_versions(shown_version(&b.version), shown_version(&a.version)));
+			found
+		})
+		.collect()
+}
+
+/* A progress bar drawn only once a scan takes long enough to wonder whether it
+ * hangs, and only to a terminal. */
+struct Progress {
+	bar: ProgressBar,
+	done: Arc<Mutex<bool>>,
+}
+
+impl Progress {
+	fn finish(self) {
+		/* Keep the bar from being shown while or after clearing it. */
+		let mut done = self.done.lock().unwrap();
+		*done = true;
+		self.bar.finish_and_clear();
+	}
+}
+
+fn progress(enabled: bool) -> Progress {
+	let bar = ProgressBar::hidden();
+	bar.set_style(ProgressStyle::with_template("{spinner} {pos}/{len} buckets, {msg}").unwrap());
+	let done = Arc::new(Mutex::new(false));
+	if enabled && io::stderr().is_terminal() {
+		let bar = bar.clone();
+		let done = done.clone();
+		thread::spawn(move || {
+			thread::sleep(Duration::from_millis(300));
+			let done = done.lock().unwrap();
+			if !*done {
+				bar.set_draw_target(ProgressDrawTarget::stderr());
+				bar.enable_steady_tick(Duration::from_millis(100));
 			}
+		});
+	}
+	Progress { bar, done }
+}
+
+/* Output held back until complete, then shown through a pager if it does not
+ * fit the terminal. Paging happens on drop, which waits for the pager. */
+struct Paged {
+	buffer: Vec<u8>,
+	height: usize,
+}
+
+impl Write for Paged {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.buffer.extend_from_slice(buf);
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+impl Drop for Paged {
+	fn drop(&mut self) {
+		if self.buffer.iter().filter(|&&byte| byte == b'\n').count() < self.height {
+			let _ = io::stdout().write_all(&self.buffer);
+		} else {
+			page(&self.buffer);
+		}
+	}
+}
+
+/* Show `content` through `PAGER`, or `less`, or `more` on Windows, or else
+ * print it as is. */
+fn page(content: &[u8]) {
+	let pager = std::env::var("PAGER").ok().filter(|pager| !pager.trim().is_empty());
+	let pagers = match &pager {
+		Some(pager) => vec![pager.as_str()],
+		None if cfg!(windows) => vec!["less", "more"],
+		None => vec!["less"],
+	};
+	for pager in pagers {
+		let mut parts = pager.split_whitespace();
+		let Some(program) = parts.next() else {
+			continue
+		};
+		let mut command = process::Command::new(program);
+		command.args(parts).stdin(process::Stdio::piped());
+		/* Let colors through, and leave the output on screen after quitting. */
+		if std::env::var_os("LESS").is_none() {
+			command.env("LESS", "FRX");
+		}
+		let Ok(mut child) = command.spawn() else {
+			continue
+		};
+		if let Some(mut stdin) = child.stdin.take() {
+			/* Quitting early closes the pipe, which is fine. */
+			let _ = stdin.write_all(content);
+		}
+		let _ = child.wait();
+		return
+	}
+	let _ = io::stdout().write_all(content);
+}
+
+fn count(results: &BucketResults) -> usize {
+	results.iter().map(|(_, entries)| entries.len()).sum()
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Serialized<'a> {
+	Single(BTreeMap<String, Vec<FindEntry>>),
+	/* Results of each line of `--stdin`. */
+	Batch(BTreeMap<&'a str, BTreeMap<String, Vec<FindEntry>>>),
+}
+
+/* What `--json-meta` adds to results. */
+#[derive(Serialize)]
+struct Meta<'a> {
+	buckets: &'a [String],
+	manifests: usize,
+	failed: usize,
+	/* Seconds spent walking buckets. */
+	elapsed: f64,
+}
+
+#[derive(Serialize)]
+struct WithMeta<'a> {
+	meta: Meta<'a>,
+	results: Serialized<'a>,
+}
+
+/* How to print results for humans. */
+struct Human {
+	/* Print `bucket/name version` lines only, for piping into other tools. */
+	flat: bool,
+	/* What ends flat lines, NUL with `--null`. */
+	end: char,
+	/* Pad names and versions into columns with `--table`. */
+	table: bool,
+	color: bool,
+	show_date: bool,
+	show_license: bool,
+	/* Terminal width to fit lines into, if any. */
+	width: Option<usize>,
+	/* Characters to cut descriptions to whatever the width, with `--description-width`. */
+	description_width: Option<usize>,
+	/* Git remotes of buckets to show along with them. */
+	remotes: HashMap<String, String>,
+}
+
+impl Human {
+	fn header(&self, bucket: &str) -> String {
+		let remote = self.remotes.get(bucket).map(|url| format!(" ({url})")).unwrap_or_default();
+		#[cfg(feature = "color")]
+		if self.color {
+			return format!("'{}' bucket{remote}:", bucket.green().bold())
+		}
+		{
+			format!("'{bucket}' bucket{remote}:")
+		}
+	}
+
+	fn app_header(&self, name: &str) -> String {
+		#[cfg(feature = "color")]
+		if self.color {
+			return format!("'{}':", name.green().bold())
 		}
+		{
+			format!("'{name}':")
+		}
+	}
+
+	/* A bucket providing an app under `app_header`. */
+	fn provider(&self, bucket: &str, entry: &FindEntry) -> String {
+		let installed = entry.installed.as_ref()
+			.map(|installed| format!(" [installed: {installed}]"))
+			.unwrap_or_default();
+		format!("	{bucket} ({}){installed}", shown_version(&entry.version))
+	}
 
-		if let Some(description) = manifest.description {
-			if description.to_lowercase().contains(&term) {
-				results.push(FindEntry {
-					name,
-					version: manifest.version,
-					bin: None,
-					description: Some(description),
+	/* Widths of the name and version columns of `entries`, if lining them up. */
+	fn columns(&self, entries: &[FindEntry]) -> Option<(usize, usize)> {
+		self.table.then(|| entries.iter().fold((0, 0), |(name, version), entry| (
+			name.max(entry.name.chars().count()),
+			version.max(shown_version(&entry.version).chars().count()),
+		)))
+	}
+
+	fn line(&self, entry: FindEntry, columns: Option<(usize, usize)>) -> String {
+		let color = self.color;
+		let FindEntry {
+			name, version, bins, description, extra, installed, license, matched_on, modified,
+			name_spans, bin_spans, bin_archs, description_spans, ..
+		} = entry;
+		let version = shown_version(&version);
+		let (name_pad, version_pad) = columns.map_or((0, 0), |(name_width, version_width)| (
+			name_width - name.chars().count(),
+			version_width - version.chars().count(),
+		));
+		let mut line = format!(
+			"	{}{} ({version}){} [{}]",
+			highlight(&name, &name_spans, color), " ".repeat(name_pad), " ".repeat(version_pad), matched_on.as_str(),
+		);
+		if let Some(modified) = modified.filter(|_| self.show_date) {
+			line += &format!(" [{}]", date(modified));
+		}
+		if let Some(license) = license.filter(|_| self.show_license) {
+			line += &format!(" [license: {license}]");
+		}
+		if !bins.is_empty() {
+			let bins = bins.iter()
+				.zip(&bin_spans)
+				.zip(&bin_archs)
+				.map(|((bin, spans), archs)| {
+					let bin = format!("'{}'", highlight(&bin.display().to_string(), spans, color));
+					if archs.is_empty() {
+						bin
+					} else {
+						let archs = archs.iter().map(|arch| arch.as_str()).collect::<Vec<_>>().join(", ");
+						format!("{bin} [{archs}]")
+					}
 				})
-			}
+				.collect::<Vec<_>>()
+				.join(", ");
+			line += &format!(" --> includes {bins}");
+		}
+		for ExtraMatch { field, text, spans } in extra {
+			line += &format!(" --> {} '{}'", extra_label(field), highlight(&text, &spans, color));
+		}
+		let installed = installed
+			.map(|installed| format!(" [installed: {installed}]"))
+			.unwrap_or_default();
+		if let Some(description) = description {
+			let room = self.width.map(|width| {
+				width.saturating_sub(visible_width(&line) + ": ".len() + installed.len())
+			});
+			let (description, spans) = match room {
+				/* Drop the description rather than break the table. */
+				Some(room) if columns.is_some() && room < 16 => return line + &installed,
+				/* Rather wrap than leave next to nothing. */
+				Some(room) => truncate(description, description_spans, room.max(16)),
+				None => (description, description_spans),
+			};
+			let (description, spans) = match self.description_width {
+				Some(max) => truncate(description, spans, max),
+				None => (description, spans),
+			};
+			line += &format!(": {}", highlight(&description, &spans, color));
 		}
+		line + &installed
 	}
+}
 
-	results.sort();
-	Ok(results)
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Color {
+	Always,
+	Never,
+	Auto,
 }
 
-/* Copied from https://github.com/shilangyu/scoop-search/blob/8b6b1809cd5d8d03735d39bc5a16e9556328927d/args.go#L25 */
-const HOOK: &str = r#"function scoop { if ($args[0] -eq "search") { scoop-searchr.exe @($args | Select-Object -Skip 1) } else { scoop.ps1 @args } }"#;
+#[derive(ValueEnum, Clone, Copy, PartialEq, Debug)]
+enum Format {
+	Human,
+	Json,
+	Toml,
+	Csv,
+	/// A Markdown table, like for pasting into issues.
+	Markdown,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Architecture {
+	#[value(name = "64bit")]
+	X64,
+	#[value(name = "32bit")]
+	X86,
+	Arm64,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Debug)]
+enum GroupBy {
+	Bucket,
+	/// Each app with the buckets and versions providing it.
+	App,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Hook {
+	#[value(alias = "powershell")]
+	Pwsh,
+	Nu,
+	Cmd,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Sort {
+	Name,
+	Version,
+	Bucket,
+	/// Latest modified manifests first.
+	Date,
+	/// Closest matches first, see `--rank`.
+	Rank,
+	/// By name byte by byte, where `App10` comes before `app2`.
+	Raw,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+	/// Print a completion script for `shell`, completing bucket names present
+	/// when it is generated.
+	Completions {
+		shell: Shell,
+	},
+	/// Run a search several times and print how long it took to stderr, with
+	/// options given before `bench` applying.
+	Bench {
+		/// How many times to run the search.
+		#[arg(long, value_name = "N", default_value = "10")]
+		iterations: NonZeroUsize,
+		/// Terms to search for, like without `bench`.
+		terms: Vec<String>,
+	},
+	/// Bring the cache of parsed manifests up to date without searching, like
+	/// after `scoop update`, so that the next search is quick.
+	Warm,
+	/// Check manifests for problems, like in CI of a bucket: failing to parse,
+	/// missing fields or bins scoop can not make shims for. Exits with 1 if
+	/// there are any.
+	Validate {
+		/// Buckets to check by name, or directories of buckets not added to
+		/// scoop, like `.` in a checkout. All buckets if none.
+		buckets: Vec<String>,
+	},
+}
+
+/// Search for apps in scoop buckets by name, binary and description.
+#[derive(Parser, Debug)]
+#[command(version, disable_help_subcommand = true)]
+struct Args {
+	#[command(subcommand)]
+	command: Option<Command>,
+	/// Terms to search for, all of which have to match, but each may match a
+	/// different field. Terms starting with `-` that are not options, or with
+	/// `!`, exclude apps they match; use `\-` or `\!` for a literal one.
+	terms: Vec<String>,
+	/// List every app in the buckets searched, instead of searching for terms.
+	#[arg(long, conflicts_with_all = ["terms", "stdin"])]
+	all: bool,
+	/// Print the hook overriding `scoop search` for this shell.
+	#[arg(long, value_enum, value_name = "SHELL", num_args = 0..=1, default_missing_value = "pwsh")]
+	hook: Option<Hook>,
+	/// Exit code when nothing is found, or nothing picked with
+	/// `--interactive`. Errors exit with 1.
+	#[arg(long, value_name = "CODE", default_value_t = 2)]
+	no_match_exit: i32,
+	/// Print names of apps starting with `PARTIAL`, one per line, for shell
+	/// completion functions. Only file names are read, so it is quick.
+	#[arg(long, value_name = "PARTIAL", conflicts_with_all = ["terms", "stdin"])]
+	complete: Option<String>,
+	/// How to print results.
+	#[arg(long, value_enum, default_value_t = Format::Human)]
+	format: Format,
+	/// Write results to this file instead of standard output.
+	#[arg(short, long, value_name = "PATH")]
+	output: Option<PathBuf>,
+	/// Print results as JSON, same as `--format json`.
+	#[arg(long, conflicts_with = "format")]
+	json: bool,
+	/// Print results as JSON under `results`, next to `meta` on the search:
+	/// the buckets scanned, how many manifests were read and failed to be,
+	/// and how many seconds it took.
+	#[arg(long, conflicts_with_all = ["format", "json", "count", "stream", "flat", "table", "dedup", "interactive"])]
+	json_meta: bool,
+	/// Match names and binaries equal to the term only.
+	#[arg(long, conflicts_with_all = ["word", "glob", "regex", "fuzzy"])]
+	exact: bool,
+	/// Match the term as a whole word only.
+	#[arg(long, conflicts_with_all = ["glob", "regex", "fuzzy"])]
+	word: bool,
+	/// Match names and binaries against the term as a shell-style glob.
+	#[arg(long, conflicts_with_all = ["regex", "fuzzy"])]
+	glob: bool,
+	/// Match the term as a regular expression.
+	#[arg(long, conflicts_with = "fuzzy")]
+	regex: bool,
+	/// Match the term fuzzily, ranking the closest matches first.
+	#[arg(long)]
+	fuzzy: bool,
+	/// Compare case-sensitively.
+	#[arg(short = 's', long)]
+	case_sensitive: bool,
+	/// Match against names, combinable with other `--*-only` flags.
+	#[arg(long)]
+	name_only: bool,
+	/// Match against binaries, combinable with other `--*-only` flags.
+	#[arg(long)]
+	bin_only: bool,
+	/// Match against descriptions, combinable with other `--*-only` flags.
+	#[arg(long)]
+	description_only: bool,
+	/// Also match against homepages, licenses, dependencies, notes and
+	/// persisted paths.
+	#[arg(long, conflicts_with_all = ["name_only", "bin_only", "description_only"])]
+	all_fields: bool,
+	/// Only search this bucket, can be repeated.
+	#[arg(long = "bucket", value_name = "NAME")]
+	buckets: Vec<String>,
+	/// Skip this bucket, can be repeated.
+	#[arg(long = "exclude-bucket", value_name = "NAME")]
+	exclude_buckets: Vec<String>,
+	/// Search the bucket in this directory instead of those of scoop, like a
+	/// checkout of its repository, without needing scoop. Can be repeated.
+	#[arg(
+		long = "bucket-path", value_name = "DIR",
+		conflicts_with_all = ["stream", "interactive", "watch", "update", "rebuild_cache", "complete"],
+	)]
+	bucket_paths: Vec<PathBuf>,
+	/// Search the bucket packed into this `.tar.gz` instead of those of scoop,
+	/// without extracting it. Can be repeated.
+	#[arg(
+		long = "archive", value_name = "FILE",
+		conflicts_with_all = ["bucket_paths", "stream", "interactive", "watch", "update", "rebuild_cache", "complete"],
+	)]
+	archives: Vec<PathBuf>,
+	/// Print the number of results instead of listing them.
+	#[arg(long, conflicts_with_all = ["json", "format"])]
+	count: bool,
+	/// With `--count`, print the number of results of each bucket.
+	#[arg(long, requires = "count")]
+	per_bucket: bool,
+	/// Print the names of buckets with any results instead of listing them.
+	#[arg(long, conflicts_with_all = ["count", "json", "json_meta", "format", "stream", "flat", "table", "dedup", "group_by", "first", "interactive"])]
+	buckets_only: bool,
+	/// Print results as soon as they are found, without sorting them.
+	#[arg(long, conflicts_with_all = ["count", "json", "format", "sort", "stdin"])]
+	stream: bool,
+	/// Print one `bucket/name version` line per result and nothing else.
+	#[arg(long, visible_alias = "plain", conflicts_with_all = ["count", "json", "format", "stdin"])]
+	flat: bool,
+	/// Line up names and versions of each bucket into columns, leaving out
+	/// descriptions that would not fit the terminal.
+	#[arg(long, conflicts_with_all = ["count", "json", "format", "stream", "flat"])]
+	table: bool,
+	/// Show apps found in several buckets once, along with the buckets and
+	/// versions providing them, highest version first.
+	#[arg(long, conflicts_with_all = ["count", "json", "format", "stream", "flat", "table"])]
+	dedup: bool,
+	/// How to group results.
+	#[arg(
+		long, value_enum, default_value_t = GroupBy::Bucket,
+		conflicts_with_all = ["count", "json", "format", "stream", "flat", "table", "dedup"],
+	)]
+	group_by: GroupBy,
+	/// Print the first result as `bucket/name version` and stop there, like
+	/// for checking whether an app exists with `--exact`.
+	#[arg(long, conflicts_with_all = ["count", "json", "json_meta", "format", "stream", "stdin", "interactive", "watch", "bucket_paths", "archives"])]
+	first: bool,
+	/// Show results through `PAGER`, or `less`, if they do not fit the
+	/// terminal. Only when printing to a terminal for people to read.
+	#[arg(long)]
+	pager: bool,
+	/// Pick an app among results while typing terms, printing its
+	/// `bucket/name`, like for `scoop install`.
+	#[arg(
+		short, long,
+		conflicts_with_all = ["count", "json", "format", "stream", "flat", "table", "dedup", "stdin", "output"],
+	)]
+	interactive: bool,
+	/// Pull buckets that are git repositories before searching.
+	#[arg(long)]
+	update: bool,
+	/// Search again whenever a manifest changes, until interrupted.
+	#[arg(long, conflicts_with_all = ["interactive", "stdin", "output"])]
+	watch: bool,
+	/// With `--flat`, end lines with NUL instead of newline, like for `xargs -0`.
+	#[arg(short = '0', long, requires = "flat")]
+	null: bool,
+	/// Search each line of standard input as terms of its own, showing the
+	/// results of each separately.
+	#[arg(long, conflicts_with = "terms")]
+	stdin: bool,
+	/// Stop after this many results in total.
+	#[arg(long, value_name = "N")]
+	limit: Option<usize>,
+	/// How to order results, by name unless `--fuzzy` is given.
+	#[arg(long, value_enum)]
+	sort: Option<Sort>,
+	/// Order results by how closely they match, same as `--sort rank`: exact
+	/// names first, then names starting with the term, names containing it,
+	/// bins and descriptions.
+	#[arg(long, conflicts_with = "sort")]
+	rank: bool,
+	/// Show when the manifest of each result was last modified.
+	#[arg(long)]
+	show_date: bool,
+	/// Show the license of each result, its SPDX identifier if it has one.
+	#[arg(long)]
+	show_license: bool,
+	/// Reverse the order of results.
+	#[arg(long)]
+	reverse: bool,
+	/// Only show apps that are installed.
+	#[arg(long, conflicts_with = "not_installed")]
+	installed: bool,
+	/// Only show apps that are not installed.
+	#[arg(long)]
+	not_installed: bool,
+	/// Look for manifests in subdirectories of buckets too.
+	#[arg(short, long)]
+	recursive: bool,
+	/// Only search bins of this architecture, besides those of all.
+	#[arg(long, value_enum)]
+	arch: Option<Architecture>,
+	/// Only show apps of at least this dotted numeric version.
+	#[arg(long, value_name = "VERSION")]
+	min_version: Option<NumericVersion>,
+	/// Only show apps of at most this dotted numeric version.
+	#[arg(long, value_name = "VERSION")]
+	max_version: Option<NumericVersion>,
+	/// Parse every manifest instead of reusing the cache of earlier searches.
+	#[arg(long)]
+	no_cache: bool,
+	/// Rebuild the cache of parsed manifests from scratch and exit.
+	#[arg(long)]
+	rebuild_cache: bool,
+	/// Read and parse manifests with this many threads, as many as there are
+	/// logical CPUs by default.
+	#[arg(short, long, value_name = "N")]
+	jobs: Option<NonZeroUsize>,
+	/// Print what the search went through and how long it took to stderr,
+	/// and where buckets come from.
+	#[arg(short, long)]
+	verbose: bool,
+	/// Print nothing but results, not even skipped manifests or that nothing
+	/// was found. Still exits with the code of `--no-match-exit` then.
+	#[arg(short, long, conflicts_with = "verbose")]
+	quiet: bool,
+	/// Show descriptions in full instead of cutting them to the terminal width.
+	#[arg(long)]
+	full_description: bool,
+	/// Cut descriptions to at most `N` characters, also when not printing to
+	/// a terminal. 0 shows them in full, like `--full-description`.
+	#[arg(long, value_name = "N", conflicts_with = "full_description")]
+	description_width: Option<usize>,
+	/// Fail if any manifest can not be read or parsed, instead of skipping it.
+	#[arg(long)]
+	strict: bool,
+	/// When to color output, `auto` honors `NO_COLOR`.
+	#[arg(long, value_enum, default_value_t = Color::Auto)]
+	color: Color,
+}
+
+fn extra_label(field: MatchField) -> &'static str {
+	match field {
+		MatchField::Shortcut => "has shortcut",
+		MatchField::Suggest => "suggests",
+		MatchField::Depends => "depends on",
+		MatchField::Persist => "persists",
+		_ => field.as_str(),
+	}
+}
+
+/* Bake the buckets present right now into the script, as static completion
+ * scripts can not ask us later. */
+fn print_completions(shell: Shell) {
+	let mut command = Args::command();
+	if let Ok(buckets) = scoop_roots(&mut Vec::new()).and_then(|roots| bucket_names(&roots)) {
+		for arg in ["buckets", "exclude_buckets"] {
+			command = command.mut_arg(arg, |arg| arg.value_parser(PossibleValuesParser::new(&buckets)));
+		}
+	}
+	let name = command.get_name().to_string();
+	clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+}
+
+/* Check the buckets named or at the paths among `buckets`, only looking for
+ * scoop if any are named. */
+fn validate_buckets(buckets: &[String], mut query: Query) -> Result<()> {
+	let (dirs, names) = buckets.iter().partition::<Vec<_>, _>(|bucket| Path::new(bucket).is_dir());
+	let mut problems = Vec::new();
+	let mut failed = Vec::new();
+	let mut manifests = 0;
+	for dir in dirs {
+		let (found, stats) = validate_dir(Path::new(dir), query.recursive)?;
+		print_warnings(&stats.warnings);
+		problems.extend(found);
+		failed.extend(stats.failed);
+		manifests += stats.manifests;
+	}
+	if !names.is_empty() || buckets.is_empty() {
+		let mut warnings = Vec::new();
+		let roots = scoop_roots(&mut warnings)?;
+		print_warnings(&warnings);
+		query.buckets.extend(names.into_iter().cloned());
+		let (found, stats) = validate(&roots, &query)?;
+		print_warnings(&stats.warnings);
+		problems.extend(found);
+		failed.extend(stats.failed);
+		manifests += stats.manifests;
+	}
+
+	for Problem { bucket, app, kind } in &problems {
+		match kind {
+			ProblemKind::MissingField(field) => println!("{bucket}/{app}: missing `{field}`"),
+			ProblemKind::NotExecutable(bin) => println!("{bucket}/{app}: bin {bin:?} is not executable"),
+		}
+	}
+	for e in &failed {
+		match std::error::Error::source(e) {
+			Some(source) => println!("{e}: {source}"),
+			None => println!("{e}"),
+		}
+	}
+	let count = problems.len() + failed.len();
+	eprintln!("{} in {}", counted(count, "problem"), counted(manifests, "manifest"));
+	if count > 0 {
+		std::process::exit(1)
+	}
+	Ok(())
+}
+
+/* Clap exits with 2 on usage errors, which is taken by no matches here. */
+fn exit_parsing(error: clap::Error) -> ! {
+	let _ = error.print();
+	std::process::exit(if error.use_stderr() { 1 } else { 0 })
+}
+
+/* Terms starting with `-` exclude apps, but clap takes them for unknown
+ * options. Rewrite those that are none into the `!` form before parsing,
+ * leaving alone anything after `--` and values of options. */
+fn dash_excludes(command: &clap::Command, cli: Vec<OsString>) -> Vec<OsString> {
+	let mut command = command.clone();
+	command.build();
+	let options = std::iter::once(&command).chain(command.get_subcommands())
+		.flat_map(clap::Command::get_arguments)
+		.collect::<Vec<_>>();
+	let shorts = options.iter()
+		.filter_map(|arg| Some((arg.get_short()?, arg.get_action().takes_values())))
+		.collect::<HashMap<_, _>>();
+	let longs_with_values = options.iter()
+		.filter(|arg| arg.get_action().takes_values())
+		.filter_map(|arg| arg.get_long())
+		.collect::<HashSet<_>>();
+
+	let mut value_next = false;
+	let mut rest = false;
+	let mut rewritten = Vec::with_capacity(cli.len());
+	for (i, arg) in cli.into_iter().enumerate() {
+		let text = match arg.to_str() {
+			Some(text) if i > 0 && !rest && !std::mem::take(&mut value_next) => text,
+			_ => {
+				rewritten.push(arg);
+				continue
+			},
+		};
+		if text == "--" {
+			rest = true;
+		} else if let Some(long) = text.strip_prefix("--") {
+			value_next = !long.contains('=') && longs_with_values.contains(long);
+		} else if let Some(word) = text.strip_prefix('-').filter(|word| !word.is_empty()) {
+			/* Options if all flags, up to one taking the rest or the next
+			 * argument as its value. */
+			let mut known = true;
+			for (at, short) in word.char_indices() {
+				match shorts.get(&short) {
+					Some(true) => {
+						value_next = at + short.len_utf8() == word.len();
+						break
+					},
+					Some(false) => (),
+					None => {
+						known = false;
+						break
+					},
+				}
+			}
+			if !known {
+				rewritten.push(format!("!{word}").into());
+				continue
+			}
+		}
+		rewritten.push(arg);
+	}
+	rewritten
+}
 
 fn main() -> Result<()> {
-	let arg = std::env::args().nth(1).unwrap_or("".to_string());
-	if arg == "--hook" {
-		println!("{}", HOOK);
+	let command = with_negations(Args::command());
+	let cli = dash_excludes(&command, std::env::args_os().collect());
+	let given = command.clone().try_get_matches_from(&cli).unwrap_or_else(|e| exit_parsing(e));
+	let config = config_args(&command, &given);
+	let args = if config.is_empty() {
+		Args::from_arg_matches(&given)?
+	} else {
+		/* Options before the rest, as that may include a subcommand. */
+		let config = config.into_iter().map(OsString::from);
+		let matches = command.try_get_matches_from(cli[..1].iter().cloned().chain(config).chain(cli[1..].iter().cloned()))
+			.unwrap_or_else(|e| exit_parsing(e));
+		Args::from_arg_matches(&matches)?
+	};
+	if let Some(Command::Completions { shell }) = args.command {
+		print_completions(shell);
 		return Ok(())
 	}
-	let term = arg;
-	let mut found = false;
-
-	let scoop_home = scoop_home()?;
-	if !scoop_home.exists() {
-		eprintln!("Failed to find a valid scoop installation");
-		std::process::exit(1);
-	}
-
-	let buckets_base = scoop_home.join("buckets");
-	for base in buckets_base.read_dir()
-		.with_context(|| format!("failed to list buckets directory: {buckets_base:?}"))?
-	{
-		let (bucket, path) = match base {
-			Ok(base) => {
-				let path = base.path();
-				let name = path.file_name().unwrap().to_string_lossy().into_owned();
-				let separate = path.join("bucket");
-				(name, if separate.exists() {
-					separate
-				} else {
-					path
-				})
+	if let Some(hook) = args.hook {
+		println!("{}", match hook {
+			Hook::Pwsh => HOOK_PWSH,
+			Hook::Nu => HOOK_NU,
+			Hook::Cmd => HOOK_CMD,
+		});
+		return Ok(())
+	}
+
+	#[cfg(not(feature = "regex"))]
+	if args.regex {
+		bail!("--regex is not available, scoop-searchr was built without the `regex` feature");
+	}
+	#[cfg(not(feature = "fuzzy"))]
+	if args.fuzzy {
+		bail!("--fuzzy is not available, scoop-searchr was built without the `fuzzy` feature");
+	}
+	#[cfg(not(feature = "interactive"))]
+	if args.interactive {
+		bail!("--interactive is not available, scoop-searchr was built without the `interactive` feature");
+	}
+	#[cfg(not(feature = "archive"))]
+	if !args.archives.is_empty() {
+		bail!("--archive is not available, scoop-searchr was built without the `archive` feature");
+	}
+
+	let raw_terms = match &args.command {
+		Some(Command::Bench { terms, .. }) => terms,
+		_ => &args.terms,
+	};
+	let (terms, _) = split_terms(raw_terms);
+	/* Listing everything takes asking for it, not forgetting terms. */
+	let searching = matches!(args.command, None | Some(Command::Bench { .. }))
+		&& !args.stdin && !args.interactive && !args.rebuild_cache && args.complete.is_none();
+	if searching && !args.all && terms.iter().all(|term| term.is_empty()) {
+		bail!("no terms to search for, pass --all to list every app");
+	}
+	let lines = if args.stdin {
+		if io::stdin().is_terminal() {
+			bail!("--stdin needs terms piped to standard input");
+		}
+		let lines = io::stdin().lines()
+			.map(|line| line.map(|line| line.trim().to_string()))
+			.filter(|line| !line.as_ref().is_ok_and(String::is_empty))
+			.collect::<io::Result<Vec<_>>>()?;
+		if lines.is_empty() {
+			bail!("no terms on standard input");
+		}
+		Some(lines)
+	} else {
+		None
+	};
+	let matcher = |term: &&str| if args.exact {
+		Ok(Matcher::exact(term, args.case_sensitive))
+	} else if args.word {
+		Ok(Matcher::word(term, args.case_sensitive))
+	} else if args.glob {
+		Matcher::glob(term, args.case_sensitive)
+	} else if args.regex {
+		/* Without the feature, `--regex` was refused already. */
+		#[cfg(feature = "regex")]
+		{ Matcher::regex(term, args.case_sensitive) }
+		#[cfg(not(feature = "regex"))]
+		unreachable!()
+	} else if args.fuzzy {
+		#[cfg(feature = "fuzzy")]
+		{ Ok(Matcher::fuzzy(term, args.case_sensitive)) }
+		#[cfg(not(feature = "fuzzy"))]
+		unreachable!()
+	} else {
+		Ok(Matcher::substring(term, args.case_sensitive))
+	};
+	let fields = if args.name_only || args.bin_only || args.description_only {
+		Fields {
+			name: args.name_only,
+			bin: args.bin_only,
+			description: args.description_only,
+			..Fields::none()
+		}
+	} else if args.all_fields {
+		Fields::all()
+	} else {
+		Fields::default()
+	};
+	let make_query = |raw: &[String]| -> Result<Query> {
+		let (terms, excludes) = split_terms(raw);
+		for term in terms.iter().chain(&excludes) {
+			check_term(term)?;
+		}
+		Ok(Query {
+			matchers: terms.iter().map(matcher).collect::<Result<_, _>>()?,
+			excludes: excludes.iter().map(matcher).collect::<Result<_, _>>()?,
+			fields,
+			buckets: args.buckets.clone(),
+			exclude_buckets: args.exclude_buckets.clone(),
+			limit: args.limit,
+			sort: args.sort.or(args.rank.then_some(Sort::Rank)).map(|sort| match sort {
+				Sort::Name => SortBy::Name,
+				Sort::Version => SortBy::Version,
+				Sort::Bucket => SortBy::Bucket,
+				Sort::Date => SortBy::Date,
+				Sort::Rank => SortBy::Rank,
+				Sort::Raw => SortBy::Raw,
+			}),
+			reverse: args.reverse,
+			cache: !args.no_cache,
+			installed: if args.installed {
+				Some(true)
+			} else if args.not_installed {
+				Some(false)
+			} else {
+				None
 			},
-			Err(e) => {
-				eprintln!("Error listing bucket directory: {e:?}");
-				continue
+			jobs: args.jobs.map(NonZeroUsize::get),
+			min_version: args.min_version.clone(),
+			max_version: args.max_version.clone(),
+			recursive: args.recursive,
+			arch: args.arch.map(|arch| match arch {
+				Architecture::X64 => Arch::X64,
+				Architecture::X86 => Arch::X86,
+				Architecture::Arm64 => Arch::Arm64,
+			}),
+		})
+	};
+	let queries = match &lines {
+		Some(lines) => lines.iter()
+			.map(|line| make_query(&line.split_whitespace().map(str::to_string).collect::<Vec<_>>()))
+			.collect::<Result<Vec<_>>>()?,
+		None => vec![make_query(raw_terms)?],
+	};
+	let query = &queries[0];
+
+	if let Some(Command::Validate { buckets }) = &args.command {
+		return validate_buckets(buckets, make_query(&[])?)
+	}
+
+	/* No scoop needed with `--bucket-path` or `--archive`. */
+	let roots = if args.bucket_paths.is_empty() && args.archives.is_empty() {
+		let mut warnings = Vec::new();
+		let roots = scoop_roots(&mut warnings)?;
+		if !roots[0].exists() {
+			eprintln!("Failed to find a valid scoop installation");
+			std::process::exit(1);
+		}
+		if let Some(partial) = &args.complete {
+			let mut out = io::stdout().lock();
+			for name in complete_names(&roots, query, partial)? {
+				writeln!(out, "{name}")?;
+			}
+			return Ok(())
+		}
+		if !args.quiet {
+			print_warnings(&warnings);
+		}
+		/* Likely a fresh installation, rather tell what to do than fail to list
+		 * what is not there. */
+		let has_buckets = |root: &PathBuf| fs::read_dir(root.join("buckets")).is_ok_and(|mut dir| dir.next().is_some());
+		if !roots.iter().any(has_buckets) {
+			eprintln!("No buckets found in {:?}, add one with `scoop bucket add main`", roots[0].join("buckets"));
+			std::process::exit(1);
+		}
+		roots
+	} else {
+		Vec::new()
+	};
+	let search = |queries: &[Query], progress: &mut dyn FnMut(&str, usize, usize)| if !args.bucket_paths.is_empty() {
+		search_dirs(&args.bucket_paths, queries)
+	} else if !args.archives.is_empty() {
+		/* Without the feature, `--archive` was refused already. */
+		#[cfg(feature = "archive")]
+		{ search_archives(&args.archives, queries) }
+		#[cfg(not(feature = "archive"))]
+		unreachable!()
+	} else {
+		search_batch_progress(&roots, queries, progress)
+	};
+
+	let warm = matches!(args.command, Some(Command::Warm));
+	if args.rebuild_cache || warm {
+		let mut parsed = 0;
+		let mut manifests = 0;
+		let mut failed = Vec::new();
+		for root in &roots {
+			let stats = update_cache(root, args.rebuild_cache)?;
+			print_warnings(&stats.warnings);
+			parsed += stats.parsed;
+			manifests += stats.manifests;
+			failed.extend(stats.failed);
+		}
+		if args.rebuild_cache {
+			println!("Cached {}", counted(parsed, "manifest"));
+		} else {
+			println!("Indexed {} new or changed, {manifests} in total", counted(parsed, "manifest"));
+		}
+		print_skipped(&failed);
+		return Ok(())
+	}
+
+	if let Some(Command::Bench { iterations, .. }) = args.command {
+		let mut timings = Vec::new();
+		let mut total = 0;
+		for _ in 0..iterations.get() {
+			let start = Instant::now();
+			let (results, stats) = search(&queries, &mut |_, _, _| ())?;
+			timings.push(start.elapsed());
+			total = results.iter().map(count).sum::<usize>();
+			if args.verbose {
+				eprintln!(
+					"Scanned {} buckets, read {} manifests ({} parsed, {} failed) in {:?}",
+					stats.buckets, stats.manifests, stats.parsed, stats.failed.len(), stats.elapsed,
+				);
 			}
+		}
+		timings.sort();
+		eprintln!(
+			"{} runs, {total} results each: min {:?}, median {:?}, max {:?}",
+			timings.len(), timings[0], timings[timings.len() / 2], timings[timings.len() - 1],
+		);
+		return Ok(())
+	}
+
+	if args.update {
+		update_buckets(&bucket_repositories(&roots, query)?, !args.quiet);
+	}
+
+	if args.watch {
+		return watch(&roots)
+	}
+
+	#[cfg(feature = "interactive")]
+	if args.interactive {
+		let (index, stats) = scoop_searchr::load_index(&roots, query)?;
+		if !args.quiet {
+			print_warnings(&stats.warnings);
+		}
+		let query_for = |input: &str| make_query(&input.split_whitespace().map(str::to_string).collect::<Vec<_>>());
+		match interactive::pick(&index, raw_terms.join(" "), query_for)? {
+			Some(app) => {
+				println!("{app}");
+				return Ok(())
+			},
+			None => std::process::exit(args.no_match_exit),
+		}
+	}
+
+	let format = if args.json || args.json_meta {
+		Format::Json
+	} else {
+		args.format
+	};
+	#[cfg(not(feature = "json"))]
+	if format == Format::Json {
+		bail!("JSON output is not available, scoop-searchr was built without the `json` feature");
+	}
+	let mut out: Box<dyn Write> = match &args.output {
+		Some(path) => {
+			if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+				fs::create_dir_all(parent)
+					.with_context(|| format!("failed to create output directory {parent:?}"))?;
+			}
+			let file = fs::File::create(path)
+				.with_context(|| format!("failed to create output file {path:?}"))?;
+			Box::new(io::BufWriter::new(file))
+		},
+		/* Only for people reading, not for what is read by other programs. */
+		None if args.pager && format == Format::Human && !args.count && !args.flat && !args.stream
+			&& io::stdout().is_terminal() =>
+		{
+			let height = terminal_size::terminal_size().map_or(usize::MAX, |(_, terminal_size::Height(height))| height as usize);
+			Box::new(Paged { buffer: Vec::new(), height })
+		},
+		None => Box::new(io::stdout().lock()),
+	};
+	if args.first {
+		/* Buckets are walked as results are taken, so none past the first match. */
+		let mut found = search_iter_with(roots.clone(), make_query(raw_terms)?);
+		let first = found.next().transpose()?;
+		if !args.quiet {
+			print_warnings(&found.stats().warnings);
+		}
+		/* Saving the cache. */
+		drop(found);
+		let Some((bucket, entry)) = first else {
+			std::process::exit(args.no_match_exit)
 		};
+		writeln!(out, "{bucket}/{} {}", entry.name, shown_version(&entry.version))?;
+		return Ok(out.flush()?)
+	}
+	let human = (format == Format::Human && !args.count && !args.buckets_only).then(|| Human {
+		flat: args.flat,
+		table: args.table,
+		end: if args.null { '\0' } else { '\n' },
+		show_date: args.show_date,
+		show_license: args.show_license,
+		color: cfg!(feature = "color") && match args.color {
+			Color::Always => true,
+			Color::Never => false,
+			Color::Auto => std::env::var_os("NO_COLOR").is_none()
+				&& args.output.is_none()
+				&& std::io::stdout().is_terminal(),
+		},
+		/* Keep results to a line each, unless going somewhere else than a terminal. */
+		width: (!args.full_description && args.description_width != Some(0)
+			&& args.output.is_none() && io::stdout().is_terminal())
+			.then(terminal_size::terminal_size)
+			.flatten()
+			.map(|(terminal_size::Width(width), _)| width as usize),
+		description_width: args.description_width.filter(|&width| width > 0),
+		remotes: if args.verbose {
+			bucket_remotes(&roots, query).unwrap_or_default()
+		} else {
+			HashMap::new()
+		},
+	});
 
-		let entries = find_manifests(&path, &term)?;
-		if entries.is_empty() {
-			continue;
+	let (results, stats, total, buckets) = if let Some(human) = human.as_ref().filter(|_| args.stream) {
+		let mut last = None::<String>;
+		let mut total = 0;
+		let mut buckets = HashSet::new();
+		let mut written = Ok(());
+		let stats = search_each(&roots, query, |bucket, entry| {
+			let print = || {
+				if human.flat {
+					return write!(out, "{bucket}/{} {}{}", entry.name, shown_version(&entry.version), human.end)
+				}
+				if last.as_deref() != Some(bucket) {
+					if last.is_some() {
+						writeln!(out)?;
+					}
+					writeln!(out, "{}", human.header(bucket))?;
+					last = Some(bucket.to_string());
+				}
+				writeln!(out, "{}", human.line(entry, None))
+			};
+			total += 1;
+			if !buckets.contains(bucket) {
+				buckets.insert(bucket.to_string());
+			}
+			if written.is_ok() {
+				written = print();
+			}
+		})?;
+		written?;
+		if last.is_some() && !human.flat {
+			writeln!(out)?;
+		}
+		(vec![Vec::new()], stats, total, buckets.len())
+	} else {
+		let progress = progress(!args.quiet);
+		let found = search(&queries, &mut |bucket, walked, total| {
+			progress.bar.set_length(total as u64);
+			progress.bar.set_position(walked as u64);
+			progress.bar.set_message(bucket.to_string());
+		});
+		progress.finish();
+		let (results, stats) = found?;
+		let total = results.iter().map(count).sum::<usize>();
+		let buckets = results.iter()
+			.flat_map(|results| results.iter().map(|(bucket, _)| bucket))
+			.collect::<HashSet<_>>()
+			.len();
+		(results, stats, total, buckets)
+	};
+	if args.strict && !stats.failed.is_empty() {
+		if !args.quiet {
+			print_warnings(&stats.warnings);
+			print_skipped(&stats.failed);
 		}
-		found = true;
+		bail!("{} failed to be read or parsed", counted(stats.failed.len(), "manifest"));
+	}
+	let found = total > 0;
 
-		println!("'{bucket}' bucket:");
-		for FindEntry { name, version, bin, description } in entries {
-			println!("	{name} ({version}){}{}", if let Some(bin) = bin {
-				format!(" --> includes '{bin:?}'")
-			} else { "".to_string() }, if let Some(description) = description {
-				format!(": {description}")
-			} else { "".to_string() });
+	/* With `--stdin`, results of each line go along with it. */
+	let labelled = results.into_iter()
+		.enumerate()
+		.map(|(i, results)| (lines.as_ref().map(|lines| lines[i].as_str()), results))
+		.collect::<Vec<_>>();
+	if args.count {
+		for (label, results) in &labelled {
+			let prefix = label.map(|label| format!("{label}: ")).unwrap_or_default();
+			if args.per_bucket {
+				for (bucket, entries) in results {
+					writeln!(out, "{prefix}{bucket}: {}", entries.len())?;
+				}
+			} else {
+				writeln!(out, "{prefix}{}", count(results))?;
+			}
+		}
+	} else if args.buckets_only {
+		for (label, results) in &labelled {
+			let prefix = label.map(|label| format!("{label}: ")).unwrap_or_default();
+			for (bucket, _) in results {
+				writeln!(out, "{prefix}{bucket}")?;
+			}
+		}
+	} else if format == Format::Json || format == Format::Toml {
+		let output = if lines.is_some() {
+			Serialized::Batch(labelled.into_iter()
+				.map(|(label, results)| (label.unwrap_or_default(), results.into_iter().collect()))
+				.collect())
+		} else {
+			Serialized::Single(labelled.into_iter().flat_map(|(_, results)| results).collect())
+		};
+		if args.json_meta {
+			let meta = Meta {
+				buckets: &stats.scanned,
+				manifests: stats.manifests,
+				failed: stats.failed.len(),
+				elapsed: stats.elapsed.as_secs_f64(),
+			};
+			writeln!(out, "{}", serde_json::to_string(&WithMeta { meta, results: output })?)?;
+		} else if format == Format::Json {
+			writeln!(out, "{}", serde_json::to_string(&output)?)?;
+		} else {
+			write!(out, "{}", toml::to_string(&output)?)?;
+		}
+	} else if format == Format::Csv {
+		let mut writer = csv::Writer::from_writer(&mut out);
+		let header = ["term", "bucket", "name", "version", "bin", "description"];
+		writer.write_record(&header[lines.is_none() as usize..])?;
+		for (label, results) in &labelled {
+			for (bucket, entries) in results {
+				for entry in entries {
+					let bins = entry.bins.iter()
+						.map(|bin| bin.to_string_lossy())
+						.collect::<Vec<_>>()
+						.join(";");
+					let record = [
+						label.unwrap_or_default(),
+						bucket,
+						&entry.name,
+						entry.version.as_deref().unwrap_or_default(),
+						&bins,
+						entry.description.as_deref().unwrap_or_default(),
+					];
+					writer.write_record(&record[label.is_none() as usize..])?;
+				}
+			}
+		}
+		writer.flush()?;
+	} else if format == Format::Markdown {
+		let cell = |text: &str| text.replace('|', "\\|").replace(['\r', '\n'], " ");
+		let header = ["Term", "Bucket", "Name", "Version", "Description"];
+		let header = &header[lines.is_none() as usize..];
+		writeln!(out, "| {} |", header.join(" | "))?;
+		writeln!(out, "|{}", " --- |".repeat(header.len()))?;
+		for (label, results) in &labelled {
+			for (bucket, entries) in results {
+				for entry in entries {
+					let row = [
+						label.unwrap_or_default(),
+						bucket,
+						&entry.name,
+						shown_version(&entry.version),
+						entry.description.as_deref().unwrap_or_default(),
+					];
+					let row = row[label.is_none() as usize..].iter().map(|text| cell(text)).collect::<Vec<_>>();
+					writeln!(out, "| {} |", row.join(" | "))?;
+				}
+			}
+		}
+	} else if let Some(human) = human.as_ref().filter(|human| human.flat) {
+		for (bucket, entries) in labelled.into_iter().flat_map(|(_, results)| results) {
+			for entry in entries {
+				write!(out, "{bucket}/{} {}{}", entry.name, shown_version(&entry.version), human.end)?;
+			}
+		}
+	} else if let Some(human) = human {
+		for (label, results) in labelled {
+			if let Some(label) = label {
+				writeln!(out, "==> {label} <==")?;
+				if results.is_empty() && !args.quiet {
+					writeln!(out, "No match found")?;
+					writeln!(out)?;
+				}
+			}
+			let total = count(&results);
+			if args.dedup {
+				let apps = dedup(results);
+				let any = !apps.is_empty();
+				for found in apps {
+					let from = found.iter()
+						.map(|(bucket, entry)| format!("{bucket} ({})", shown_version(&entry.version)))
+						.collect::<Vec<_>>()
+						.join(", ");
+					let Some((_, best)) = found.into_iter().next() else {
+						continue
+					};
+					writeln!(out, "{}", human.line(best, None))?;
+					writeln!(out, "		from {from}")?;
+				}
+				if any {
+					writeln!(out)?;
+				}
+			} else if args.group_by == GroupBy::App {
+				for mut found in by_app(results) {
+					found.sort_by(|(a, _), (b, _)| a.cmp(b));
+					let Some((_, first)) = found.first() else {
+						continue
+					};
+					writeln!(out, "{}", human.app_header(&first.name))?;
+					for (bucket, entry) in &found {
+						writeln!(out, "{}", human.provider(bucket, entry))?;
+					}
+					writeln!(out)?;
+				}
+			} else {
+				for (bucket, entries) in results {
+					writeln!(out, "{}", human.header(&bucket))?;
+					let columns = human.columns(&entries);
+					for entry in entries {
+						writeln!(out, "{}", human.line(entry, columns))?;
+					}
+					writeln!(out)?;
+				}
+			}
+			if total > 0 && !args.quiet && stats.limited {
+				writeln!(out, "... (showing the first {})", counted(total, "result"))?;
+			}
 		}
-		println!();
+	}
+
+	/* Exiting skips destructors, so make sure everything is written first. */
+	out.flush()?;
+	drop(out);
+
+	if !args.quiet {
+		print_warnings(&stats.warnings);
+		print_skipped(&stats.failed);
+	}
+	if args.verbose {
+		for (name, version) in &stats.unversioned {
+			match version {
+				Some(version) => eprintln!("Left out {name}, its version {version:?} is not dotted numeric"),
+				None => eprintln!("Left out {name}, it has no version"),
+			}
+		}
+		for paths in &stats.duplicates {
+			let paths = paths.iter().map(|path| format!("{path:?}")).collect::<Vec<_>>().join(", ");
+			eprintln!("Manifests differing only in case, one may shadow the others: {paths}");
+		}
+		eprintln!(
+			"Scanned {} buckets, read {} manifests ({} parsed, {} failed) in {:?}",
+			stats.buckets, stats.manifests, stats.parsed, stats.failed.len(), stats.elapsed,
+		);
+	}
+	if found && !args.count && !args.quiet {
+		eprintln!("{} across {}", counted(total, "result"), counted(buckets, "bucket"));
 	}
 
 	if found {
 		Ok(())
+	} else if format != Format::Human || args.count || args.buckets_only || args.flat || args.quiet {
+		std::process::exit(args.no_match_exit)
 	} else {
 		println!("No match found");
-		std::process::exit(1)
+		if let Some(term) = terms.first().filter(|term| terms.len() == 1 && !term.is_empty()) {
+			let similar = similar_names(&roots, query, term, 3).unwrap_or_default();
+			if !similar.is_empty() {
+				println!("Did you mean: {}?", similar.join(", "));
+			}
+		}
+		std::process::exit(args.no_match_exit)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse(cli: &[&str]) -> Args {
+		let command = with_negations(Args::command());
+		let cli = cli.iter().map(OsString::from).collect();
+		Args::from_arg_matches(&command.clone().try_get_matches_from(dash_excludes(&command, cli)).unwrap()).unwrap()
+	}
+
+	#[test]
+	fn dash_terms_exclude() {
+		let args = parse(&["scoop-searchr", "python", "-test"]);
+		assert_eq!(split_terms(&args.terms), (vec!["python"], vec!["test"]));
+	}
+
+	#[test]
+	fn options_are_not_terms() {
+		let args = parse(&["scoop-searchr", "git", "-q", "--", "-tools"]);
+		assert!(args.quiet);
+		assert_eq!(split_terms(&args.terms), (vec!["git"], vec!["tools"]));
+	}
+
+	#[test]
+	fn negations_turn_flags_off() {
+		assert!(!parse(&["scoop-searchr", "--rank", "git", "--no-rank"]).rank);
+		assert!(parse(&["scoop-searchr", "--no-rank", "git", "--rank"]).rank);
 	}
 }